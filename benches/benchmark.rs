@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use criterion::black_box;
-use criterion::{criterion_group, criterion_main, Criterion, ParameterizedBenchmark};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use image::{DynamicImage, GenericImageView, GrayImage, Luma};
 
 fn open_image() -> DynamicImage {
@@ -36,24 +36,80 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 
     let (w, h) = (160, 90);
-    c.bench(
-        &format!("{w}x{h} to ({w}-i)x{h}", w = w, h = h),
-        ParameterizedBenchmark::new(
-            "seamcarving",
-            move |b, &i| {
-                let gray_img = black_box(gray_bench_image(w, h));
-                b.iter(|| seamcarving::resize(&gray_img, w - i, h))
-            },
-            vec![w / 16, w / 8, w / 6, w / 4, w / 2, 2 * w / 3],
-        ).with_function(
-            "imageproc",
-            move |b, &i| {
-                let gray_img = black_box(gray_bench_image(w, h));
-                b.iter(||
-                    imageproc::seam_carving::shrink_width(&gray_img, w - i))
-            },
-        ),
-    );
+    let mut group = c.benchmark_group(format!("{w}x{h} to ({w}-i)x{h}", w = w, h = h));
+    for &i in &[w / 16, w / 8, w / 6, w / 4, w / 2, 2 * w / 3] {
+        group.bench_with_input(BenchmarkId::new("seamcarving", i), &i, |b, &i| {
+            let gray_img = black_box(gray_bench_image(w, h));
+            b.iter(|| seamcarving::resize(&gray_img, w - i, h))
+        });
+        group.bench_with_input(BenchmarkId::new("imageproc", i), &i, |b, &i| {
+            let gray_img = black_box(gray_bench_image(w, h));
+            b.iter(|| imageproc::seam_carving::shrink_width(&gray_img, w - i))
+        });
+    }
+    group.finish();
+
+    c.bench_function("2000x100 to 1000x100", |b| {
+        let gray_img = black_box(gray_bench_image(2000, 100));
+        b.iter(|| seamcarving::resize(&gray_img, 1000, 100))
+    });
+
+    // Several seams per pass trade quality for speed:
+    // `resize eval` in the resize example compares the quality of the results
+    let mut group = c.benchmark_group("100x100 to 50x50, seams per pass");
+    let img = open_image().to_rgb8();
+    for &per_pass in &[1, 4, 16] {
+        let options = seamcarving::Options::default().seams_per_pass(per_pass);
+        group.bench_with_input(BenchmarkId::from_parameter(per_pass), &options, |b, options| {
+            b.iter(|| seamcarving::resize_with_options(&img, 50, 50, options))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("400x400 to 200x200, pyramid factor");
+    let img = image::imageops::resize(&img, 400, 400, image::imageops::FilterType::Triangle);
+    for &factor in &[1, 2, 4] {
+        let options = seamcarving::Options::default().pyramid(factor);
+        group.bench_with_input(BenchmarkId::from_parameter(factor), &options, |b, options| {
+            b.iter(|| seamcarving::resize_with_options(&img, 200, 200, options))
+        });
+    }
+    group.finish();
+
+    // Images with u16 channels take the generic path, and give the same seams
+    let mut group = c.benchmark_group("u8 fast path");
+    let rgb = image::imageops::resize(&img, 300, 300, image::imageops::FilterType::Triangle);
+    let rgb16: image::ImageBuffer<image::Rgb<u16>, Vec<u16>> =
+        image::ImageBuffer::from_raw(300, 300, rgb.iter().map(|&c| u16::from(c)).collect())
+            .unwrap();
+    group.bench_function("300x300 to 200x200 Rgb<u8>", |b| {
+        b.iter(|| seamcarving::resize(&rgb, 200, 200))
+    });
+    group.bench_function("300x300 to 200x200 Rgb<u16>", |b| {
+        b.iter(|| seamcarving::resize(&rgb16, 200, 200))
+    });
+    let gray = gray_bench_image(2000, 100);
+    let gray16: image::ImageBuffer<Luma<u16>, Vec<u16>> =
+        image::ImageBuffer::from_fn(2000, 100, |x, y| Luma([u16::from(gray[(x, y)][0])]));
+    group.bench_function("2000x100 to 1000x100 Luma<u8>", |b| {
+        b.iter(|| seamcarving::resize(&gray, 1000, 100))
+    });
+    group.bench_function("2000x100 to 1000x100 Luma<u16>", |b| {
+        b.iter(|| seamcarving::resize(&gray16, 1000, 100))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("copy carved 1000x1000 view");
+    let rgb_img = image::RgbImage::from_fn(1000, 1000, |x, y| image::Rgb([x as u8, y as u8, 0]));
+    let view = seamcarving::resize_view(&rgb_img, 990, 990);
+    group.bench_function("image_view_to_buffer", |b| {
+        b.iter(|| seamcarving::image_view_to_buffer(black_box(&view)))
+    });
+    group.bench_function("copy_into", |b| {
+        let mut out = image::RgbImage::new(0, 0);
+        b.iter(|| black_box(&view).copy_into(&mut out))
+    });
+    group.finish();
 }
 
 criterion_group! {
@@ -0,0 +1,25 @@
+fn main() {
+    #[cfg(feature = "capi")]
+    capi::build();
+}
+
+#[cfg(feature = "capi")]
+mod capi {
+    use std::env;
+    use std::path::Path;
+
+    /// Generates the C header in OUT_DIR.
+    /// tests/capi.rs checks that the copy in include/ is up to date.
+    pub fn build() {
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let config = cbindgen::Config::from_file("cbindgen.toml").expect("invalid cbindgen.toml");
+        let out_dir = env::var("OUT_DIR").unwrap();
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src("src/capi.rs")
+            .generate()
+            .expect("unable to generate the C header")
+            .write_to_file(Path::new(&out_dir).join("seamcarving.h"));
+    }
+}
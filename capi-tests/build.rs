@@ -0,0 +1,10 @@
+/// Compiles the C program run by tests/capi.rs, against the header of the crate
+fn main() {
+    println!("cargo:rerun-if-changed=capi.c");
+    println!("cargo:rerun-if-changed=../include/seamcarving.h");
+    cc::Build::new()
+        .file("capi.c")
+        .include("../include")
+        .warnings_into_errors(true)
+        .compile("capi_test");
+}
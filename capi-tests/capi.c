@@ -0,0 +1,67 @@
+/* Exercises the C interface. Compiled by build.rs, run by tests/capi.rs */
+#include <stdio.h>
+#include <string.h>
+
+#include "seamcarving.h"
+
+#define WIDTH 6
+#define HEIGHT 4
+#define STRIDE (WIDTH * 4 + 3)
+
+#define CHECK(condition)                                         \
+    do {                                                         \
+        if (!(condition)) {                                      \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #condition); \
+            return 1;                                            \
+        }                                                        \
+    } while (0)
+
+int run_capi_tests(void) {
+    /* A noisy image with a flat column, and padding at the end of each row */
+    uint8_t data[HEIGHT * STRIDE];
+    memset(data, 0xAA, sizeof(data));
+    for (int y = 0; y < HEIGHT; y++) {
+        for (int x = 0; x < WIDTH; x++) {
+            uint8_t value = x == 2 ? 100 : (uint8_t)((x * 97 + y * 61) % 251);
+            uint8_t *pixel = &data[y * STRIDE + x * 4];
+            pixel[0] = pixel[1] = pixel[2] = value;
+            pixel[3] = 255;
+        }
+    }
+
+    uint8_t resized[(WIDTH - 2) * HEIGHT * 4];
+    CHECK(seamcarving_resize_rgba8(data, WIDTH, HEIGHT, STRIDE, WIDTH - 2, HEIGHT, resized)
+          == SEAMCARVING_STATUS_OK);
+    for (int i = 0; i < (int)sizeof(resized); i++) {
+        CHECK(resized[i] != 0xAA);
+    }
+    CHECK(seamcarving_resize_rgba8(NULL, WIDTH, HEIGHT, STRIDE, 1, 1, resized)
+          == SEAMCARVING_STATUS_NULL_POINTER);
+    CHECK(seamcarving_resize_rgba8(data, WIDTH, HEIGHT, WIDTH * 4 - 1, 1, 1, resized)
+          == SEAMCARVING_STATUS_INVALID_STRIDE);
+    CHECK(seamcarving_resize_rgba8(data, WIDTH, HEIGHT, STRIDE, WIDTH + 1, HEIGHT, resized)
+          == SEAMCARVING_STATUS_INVALID_SIZE);
+
+    /* Removing seams one by one gives the same result */
+    SeamcarvingCarvable *carvable = NULL;
+    CHECK(seamcarving_carvable_new(data, WIDTH, HEIGHT, STRIDE, &carvable) == SEAMCARVING_STATUS_OK);
+    CHECK(seamcarving_carvable_remove_seam(carvable) == SEAMCARVING_STATUS_OK);
+    CHECK(seamcarving_carvable_remove_seam(carvable) == SEAMCARVING_STATUS_OK);
+    uint32_t width = 0, height = 0;
+    CHECK(seamcarving_carvable_dimensions(carvable, &width, &height) == SEAMCARVING_STATUS_OK);
+    CHECK(width == WIDTH - 2 && height == HEIGHT);
+    uint8_t result[sizeof(resized)];
+    CHECK(seamcarving_carvable_get_result(carvable, result, width * 4) == SEAMCARVING_STATUS_OK);
+    CHECK(memcmp(result, resized, sizeof(resized)) == 0);
+    CHECK(seamcarving_carvable_get_result(carvable, result, width * 4 - 1)
+          == SEAMCARVING_STATUS_INVALID_STRIDE);
+    while (width > 0) {
+        CHECK(seamcarving_carvable_remove_seam(carvable) == SEAMCARVING_STATUS_OK);
+        width--;
+    }
+    CHECK(seamcarving_carvable_remove_seam(carvable) == SEAMCARVING_STATUS_NO_SEAM_LEFT);
+    seamcarving_carvable_free(carvable);
+    seamcarving_carvable_free(NULL);
+    CHECK(seamcarving_carvable_remove_seam(NULL) == SEAMCARVING_STATUS_NULL_POINTER);
+    return 0;
+}
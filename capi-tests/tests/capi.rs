@@ -0,0 +1,14 @@
+use std::os::raw::c_int;
+
+// Links the functions called by the C program
+use seamcarving as _;
+
+// Compiled from capi.c by the build script
+extern "C" {
+    fn run_capi_tests() -> c_int;
+}
+
+#[test]
+fn c_program() {
+    assert_eq!(unsafe { run_capi_tests() }, 0);
+}
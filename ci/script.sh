@@ -11,10 +11,18 @@ if [[ "$TARGET" ]]; then
     TARGET_FLAG="--target=$TARGET"
 fi
 
-cargo build $TARGET_FLAG
+if [[ "$MSRV" ]]; then
+    # Lock the most recent dependencies that support the rust-version of Cargo.toml
+    rustup toolchain install stable --profile minimal;
+    CARGO_RESOLVER_INCOMPATIBLE_RUST_VERSIONS=fallback cargo +stable generate-lockfile;
+fi
+
+cargo build $TARGET_FLAG $BUILD_FLAGS
 
 if [[ -z "$SKIP_TESTS" ]]; then
     cargo test;
+    cargo test --features capi --test capi;
+    cargo test --manifest-path capi-tests/Cargo.toml;
 fi
 
 if [[ "$DO_BENCHMARKS" ]]; then
@@ -1,18 +1,31 @@
 use std::env;
 use std::error::Error;
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use image::GenericImageView;
+use seamcarving::eval::evaluate;
+use seamcarving::{
+    read_apng, read_gif, resize_animation, resize_with_plan, write_apng, write_gif, Options, Solver,
+};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
+    if args.len() == 5 && args[1] == "eval" {
+        return evaluate_folder(Path::new(&args[2]), args[3].parse()?, args[4].parse()?);
+    }
     if args.len() < 4 {
-        return Err("Usage: resize /path/to/image.jpg desired_width desired_height".into());
+        return Err(
+            "Usage: resize /path/to/image.jpg desired_width desired_height\n       \
+                    resize eval /path/to/folder width_percent height_percent"
+                .into(),
+        );
     }
     let image_path: PathBuf = args[1].parse()?;
     let width: u32 = args[2].parse()?;
     let height: u32 = args[3].parse()?;
 
-    let input_image = image::open(&image_path)?;
-    let resized = seamcarving::resize(&input_image, width, height);
     let output_path = image_path.with_file_name(format!(
         "{}_resized.{}",
         image_path
@@ -24,10 +37,111 @@ fn main() -> Result<(), Box<dyn Error>> {
             .expect("invalid file extension")
             .to_string_lossy()
     ));
-    resized.save(&output_path)?;
+    if !resize_if_animated(&image_path, &output_path, width, height)? {
+        let input_image = image::open(&image_path)?;
+        let resized = seamcarving::resize(&input_image, width, height);
+        resized.save(&output_path)?;
+    }
     println!(
         "Resized image successfully written to {}",
         output_path.to_string_lossy()
     );
     Ok(())
 }
+
+/// Resizes all the frames of animated GIF and PNG files.
+/// Returns false if the input is not an animation.
+fn resize_if_animated(
+    input: &Path,
+    output: &Path,
+    width: u32,
+    height: u32,
+) -> Result<bool, Box<dyn Error>> {
+    let extension = input
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    let reader = || File::open(input).map(BufReader::new);
+    let frames = match extension.as_deref() {
+        Some("gif") => read_gif(reader()?)?,
+        Some("png") | Some("apng") => read_apng(reader()?)?,
+        _ => return Ok(false),
+    };
+    if frames.len() < 2 {
+        return Ok(false);
+    }
+    let resized = resize_animation(&frames, width, height);
+    let writer = BufWriter::new(File::create(output)?);
+    if extension.as_deref() == Some("gif") {
+        write_gif(writer, &resized)?;
+    } else {
+        write_apng(writer, &resized)?;
+    }
+    Ok(true)
+}
+
+/// Resizes every image of a folder with several sets of options, and prints the metrics
+/// of each result as tab-separated values.
+/// A `name_mask.png` file next to `name.jpg` marks the pixels that should be protected.
+fn evaluate_folder(
+    folder: &Path,
+    width_percent: u32,
+    height_percent: u32,
+) -> Result<(), Box<dyn Error>> {
+    let strategies = [
+        ("default", Options::default()),
+        ("8 seams per pass", Options::default().seams_per_pass(8)),
+        ("pyramid 2", Options::default().pyramid(2)),
+        (
+            "forward graph cut",
+            Options::default().solver(Solver::GraphCut {
+                forward_energy: true,
+            }),
+        ),
+    ];
+    let mut paths: Vec<PathBuf> = fs::read_dir(folder)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    paths.sort();
+    println!(
+        "image\toptions\tbidirectional similarity\tremoved energy\tedge distortion\tprotected lost"
+    );
+    for path in paths {
+        let stem = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        if stem.ends_with("_mask") || stem.ends_with("_resized") {
+            continue;
+        }
+        let img = match image::open(&path) {
+            Ok(img) => img,
+            // Not an image
+            Err(_) => continue,
+        };
+        let mask_path = path.with_file_name(format!("{}_mask.png", stem));
+        let mask = if mask_path.exists() {
+            Some(image::open(&mask_path)?.to_luma8())
+        } else {
+            None
+        };
+        let width = img.width() * width_percent / 100;
+        let height = img.height() * height_percent / 100;
+        for (name, options) in &strategies {
+            let (_, plan) = resize_with_plan(&img, width, height, options);
+            let metrics = evaluate(&img, &plan, mask.as_ref())?;
+            let protected_lost = metrics
+                .protected_lost
+                .map_or(String::from("-"), |lost| format!("{:.3}", lost));
+            println!(
+                "{}\t{}\t{:.1}\t{}\t{:.1}\t{}",
+                path.file_name().expect("a file").to_string_lossy(),
+                name,
+                metrics.bidirectional_similarity,
+                metrics.removed_energy,
+                metrics.edge_distortion,
+                protected_lost
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+/* Generated by cbindgen from src/capi.rs: do not edit */
+
+#ifndef SEAMCARVING_H
+#define SEAMCARVING_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The result of a function of the C interface
+typedef enum SeamcarvingStatus {
+  SEAMCARVING_STATUS_OK = 0,
+  // A required pointer was null
+  SEAMCARVING_STATUS_NULL_POINTER = 1,
+  // The stride is smaller than a row of pixels
+  SEAMCARVING_STATUS_INVALID_STRIDE = 2,
+  // The target size is larger than the image
+  SEAMCARVING_STATUS_INVALID_SIZE = 3,
+  // The image has no seam left to remove
+  SEAMCARVING_STATUS_NO_SEAM_LEFT = 4,
+  // An unexpected internal error
+  SEAMCARVING_STATUS_PANIC = 5,
+} SeamcarvingStatus;
+
+// An image from which vertical seams can be removed one by one, like [Carvable]
+typedef struct SeamcarvingCarvable SeamcarvingCarvable;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Resizes an RGBA image to a lower width and height.
+//
+// `out` must have room for `target_width * target_height * 4` bytes,
+// and receives the resized image without padding between rows.
+//
+// # Safety
+// `data` must point to `(height - 1) * stride + width * 4` readable bytes,
+// and `out` to `target_width * target_height * 4` writable bytes.
+enum SeamcarvingStatus seamcarving_resize_rgba8(const uint8_t *data,
+                                                uint32_t width,
+                                                uint32_t height,
+                                                size_t stride,
+                                                uint32_t target_width,
+                                                uint32_t target_height,
+                                                uint8_t *out);
+
+// Copies an RGBA image into a new [SeamcarvingCarvable], stored in `*carvable`.
+// It must be freed with [seamcarving_carvable_free].
+//
+// # Safety
+// `data` must point to `(height - 1) * stride + width * 4` readable bytes,
+// and `carvable` must be a valid pointer.
+enum SeamcarvingStatus seamcarving_carvable_new(const uint8_t *data,
+                                                uint32_t width,
+                                                uint32_t height,
+                                                size_t stride,
+                                                struct SeamcarvingCarvable **carvable);
+
+// Removes a vertical seam, diminishing the width of the image by 1
+//
+// # Safety
+// `carvable` must come from [seamcarving_carvable_new] and not be freed.
+enum SeamcarvingStatus seamcarving_carvable_remove_seam(struct SeamcarvingCarvable *carvable);
+
+// Stores the current size of the image in `*width` and `*height`
+//
+// # Safety
+// `carvable` must come from [seamcarving_carvable_new] and not be freed,
+// and `width` and `height` must be valid pointers.
+enum SeamcarvingStatus seamcarving_carvable_dimensions(const struct SeamcarvingCarvable *carvable,
+                                                       uint32_t *width,
+                                                       uint32_t *height);
+
+// Copies the current image to `out`, with `stride` bytes between the starts of two rows
+//
+// # Safety
+// `carvable` must come from [seamcarving_carvable_new] and not be freed,
+// and `out` must point to `(height - 1) * stride + width * 4` writable bytes,
+// for the size given by [seamcarving_carvable_dimensions].
+enum SeamcarvingStatus seamcarving_carvable_get_result(const struct SeamcarvingCarvable *carvable,
+                                                       uint8_t *out,
+                                                       size_t stride);
+
+// Frees a [SeamcarvingCarvable]. Does nothing if `carvable` is null.
+//
+// # Safety
+// `carvable` must come from [seamcarving_carvable_new] and not be freed already.
+void seamcarving_carvable_free(struct SeamcarvingCarvable *carvable);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* SEAMCARVING_H */
@@ -0,0 +1,306 @@
+//! Python bindings for seamcarving, operating on NumPy arrays.
+//!
+//! Images are `uint8` arrays of shape `(height, width)` or `(height, width, channels)`.
+//! Contiguous arrays are read without being copied, and results are moved
+//! to NumPy without copies.
+use std::mem::ManuallyDrop;
+
+use numpy::ndarray::ArrayViewD;
+use numpy::{
+    IntoPyArray, PyArrayDyn, PyArrayMethods, PyReadonlyArray2, PyReadonlyArrayDyn,
+    PyUntypedArrayMethods,
+};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use seamcarving::raw::{
+    energy_map_raw, resize_raw_with_energy, EnergySource, RawCarvable, RawImage,
+};
+use seamcarving::Options;
+
+/// Resizes an image to a lower width and height, removing seams of low energy.
+///
+/// `energy` gives the energy of each pixel instead of the default gradient energy.
+/// Pixels where `mask` is true are only removed when no seam can avoid them,
+/// as long as the energy of a seam stays below 2^30: with the gradient energy,
+/// in images of up to 8256 / channels pixels in width and height.
+/// In larger images, they are only avoided as much as possible.
+#[pyfunction]
+#[pyo3(signature = (array, width, height, energy = None, mask = None))]
+fn resize<'py>(
+    py: Python<'py>,
+    array: PyReadonlyArrayDyn<'py, u8>,
+    width: u32,
+    height: u32,
+    energy: Option<&Bound<'py, PyAny>>,
+    mask: Option<PyReadonlyArray2<'py, bool>>,
+) -> PyResult<Bound<'py, PyArrayDyn<u8>>> {
+    let layout = Layout::of(&array)?;
+    let energies = energies(&layout, energy, mask)?;
+    let data = contiguous(array.as_array());
+    let img = layout.image(&data)?;
+    let source = energies.source(&layout)?;
+    let (carved, _) =
+        py.detach(|| resize_raw_with_energy(&img, source, width, height, &Options::default()));
+    let (width, height) = (width.min(layout.width), height.min(layout.height));
+    layout.numpy_array(py, carved, width, height)
+}
+
+/// The gradient energy of each pixel of an image, as a `uint32` array of shape `(height, width)`
+#[pyfunction]
+fn energy_map<'py>(
+    py: Python<'py>,
+    array: PyReadonlyArrayDyn<'py, u8>,
+) -> PyResult<Bound<'py, PyArrayDyn<u32>>> {
+    let layout = Layout::of(&array)?;
+    let data = contiguous(array.as_array());
+    let img = layout.image(&data)?;
+    let energies = py.detach(|| energy_map_raw(&img));
+    let shape = [layout.height as usize, layout.width as usize];
+    energies.into_pyarray(py).reshape(shape.as_slice())
+}
+
+/// An image from which vertical seams can be removed one at a time.
+///
+/// The image is copied, so later changes to the array have no effect.
+#[pyclass(name = "Carvable")]
+struct PyCarvable {
+    // Borrows the leaked buffers below, so it must be dropped before them
+    carvable: ManuallyDrop<RawCarvable<'static, u8>>,
+    pixels: &'static [u8],
+    energies: Option<&'static [u32]>,
+    layout: Layout,
+    seams: Vec<Vec<(u32, u32)>>,
+}
+
+#[pymethods]
+impl PyCarvable {
+    #[new]
+    #[pyo3(signature = (array, energy = None, mask = None))]
+    fn new(
+        array: PyReadonlyArrayDyn<'_, u8>,
+        energy: Option<&Bound<'_, PyAny>>,
+        mask: Option<PyReadonlyArray2<'_, bool>>,
+    ) -> PyResult<Self> {
+        let layout = Layout::of(&array)?;
+        let energies = energies(&layout, energy, mask)?;
+        let pixels: &'static [u8] = Box::leak(array.as_array().iter().copied().collect());
+        let img = layout.image(pixels)?;
+        let leak = |values: Vec<u32>| -> &'static [u32] { Box::leak(values.into_boxed_slice()) };
+        let (source, energies) = match energies {
+            Energies::Gradient => (EnergySource::Gradient, None),
+            Energies::Custom(values) => {
+                let values = leak(values);
+                (EnergySource::Custom(layout.map(values)?), Some(values))
+            }
+            Energies::Biased(values) => {
+                let values = leak(values);
+                (EnergySource::Biased(layout.map(values)?), Some(values))
+            }
+        };
+        Ok(PyCarvable {
+            carvable: ManuallyDrop::new(RawCarvable::new(img, source, &Options::default())),
+            pixels,
+            energies,
+            layout,
+            seams: Vec::new(),
+        })
+    }
+
+    /// Removes the lowest-energy vertical seam, and returns the `(x, y)` positions
+    /// of its pixels in the original image, from top to bottom.
+    /// Returns `None` if the image is empty.
+    fn remove_seam(&mut self, py: Python<'_>) -> Option<Vec<(u32, u32)>> {
+        let carvable = &mut *self.carvable;
+        let seam = py.detach(|| carvable.remove_seam())?;
+        self.seams.push(seam.clone());
+        Some(seam)
+    }
+
+    /// The seams removed so far, as returned by `remove_seam`
+    #[getter]
+    fn seams(&self) -> Vec<Vec<(u32, u32)>> {
+        self.seams.clone()
+    }
+
+    /// The carved image, as a new array
+    fn result<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArrayDyn<u8>>> {
+        let (width, height) = self.carvable.dimensions();
+        self.layout.numpy_array(py, self.carvable.result(), width, height)
+    }
+}
+
+impl Drop for PyCarvable {
+    fn drop(&mut self) {
+        // Safety: the carvable is dropped first, and nothing else borrows the buffers,
+        // which were leaked from boxes in `new`
+        unsafe {
+            ManuallyDrop::drop(&mut self.carvable);
+            drop(Box::from_raw(self.pixels as *const [u8] as *mut [u8]));
+            if let Some(energies) = self.energies {
+                drop(Box::from_raw(energies as *const [u32] as *mut [u32]));
+            }
+        }
+    }
+}
+
+/// Energies given from Python, with the size of the image
+enum Energies {
+    Gradient,
+    Custom(Vec<u32>),
+    Biased(Vec<u32>),
+}
+
+impl Energies {
+    fn source<'a>(&'a self, layout: &Layout) -> PyResult<EnergySource<'a>> {
+        Ok(match self {
+            Energies::Gradient => EnergySource::Gradient,
+            Energies::Custom(values) => EnergySource::Custom(layout.map(values)?),
+            Energies::Biased(values) => EnergySource::Biased(layout.map(values)?),
+        })
+    }
+}
+
+/// Reads the `energy` and `mask` arguments.
+/// Energies can be given as unsigned integers or as floats, which are rounded.
+fn energies(
+    layout: &Layout,
+    energy: Option<&Bound<'_, PyAny>>,
+    mask: Option<PyReadonlyArray2<'_, bool>>,
+) -> PyResult<Energies> {
+    let shape = [layout.height as usize, layout.width as usize];
+    let check = |name: &str, actual: &[usize]| {
+        if actual == shape {
+            Ok(())
+        } else {
+            let message = format!("{} has shape {:?}, expected {:?}", name, actual, shape);
+            Err(PyValueError::new_err(message))
+        }
+    };
+    let custom = match energy {
+        None => None,
+        Some(energy) => {
+            if let Ok(values) = energy.extract::<PyReadonlyArray2<'_, u32>>() {
+                check("energy", values.shape())?;
+                Some(contiguous(values.as_array().into_dyn()).into_owned())
+            } else {
+                let values: PyReadonlyArray2<'_, f64> = energy.extract()?;
+                check("energy", values.shape())?;
+                let values = values.as_array();
+                Some(values.iter().map(|&e| e.max(0.).round() as u32).collect())
+            }
+        }
+    };
+    let protected = match mask {
+        None => None,
+        Some(mask) => {
+            check("mask", mask.shape())?;
+            let highest = match &custom {
+                Some(values) => values.iter().copied().max().unwrap_or(0),
+                None => 2 * 255 * 255 * layout.channels.unwrap_or(1) as u32,
+            };
+            let bias = protection(layout, highest);
+            let mask = mask.as_array();
+            Some(mask.iter().map(|&m| if m { bias } else { 0 }).collect::<Vec<_>>())
+        }
+    };
+    Ok(match (custom, protected) {
+        (None, None) => Energies::Gradient,
+        (Some(custom), None) => Energies::Custom(custom),
+        (Some(custom), Some(protected)) => Energies::Custom(
+            custom.iter().zip(&protected).map(|(&c, &p)| c.saturating_add(p)).collect(),
+        ),
+        (None, Some(protected)) => Energies::Biased(protected),
+    })
+}
+
+/// Energy added to the pixels of a mask, higher than the energy of any seam
+/// that avoids it, given the highest energy of a pixel.
+///
+/// Seam energies saturate at 2^30 - 1, so seams that avoid the mask can only be told apart
+/// from the ones that go through it while their energy stays below that.
+fn protection(layout: &Layout, highest: u32) -> u32 {
+    let longest_seam = layout.width.max(layout.height);
+    highest.saturating_mul(longest_seam).saturating_add(1)
+}
+
+/// The dimensions of an image array
+#[derive(Clone, Copy)]
+struct Layout {
+    width: u32,
+    height: u32,
+    // None for arrays with 2 dimensions
+    channels: Option<usize>,
+}
+
+impl Layout {
+    fn of(array: &PyReadonlyArrayDyn<'_, u8>) -> PyResult<Self> {
+        let dimension = |d: usize| {
+            u32::try_from(d).map_err(|_| PyValueError::new_err("the image is too large"))
+        };
+        match *array.shape() {
+            [height, width] => Ok(Layout {
+                width: dimension(width)?,
+                height: dimension(height)?,
+                channels: None,
+            }),
+            [height, width, channels] if channels > 0 => Ok(Layout {
+                width: dimension(width)?,
+                height: dimension(height)?,
+                channels: Some(channels),
+            }),
+            _ => Err(PyValueError::new_err(
+                "expected an array of shape (height, width) or (height, width, channels)",
+            )),
+        }
+    }
+
+    fn channels(&self) -> usize {
+        self.channels.unwrap_or(1)
+    }
+
+    fn image<'a>(&self, data: &'a [u8]) -> PyResult<RawImage<'a, u8>> {
+        let stride = self.width as usize * self.channels();
+        RawImage::new(data, self.width, self.height, stride, self.channels())
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// An energy map with the size of the image
+    fn map<'a>(&self, data: &'a [u32]) -> PyResult<RawImage<'a, u32>> {
+        RawImage::new(data, self.width, self.height, self.width as usize, 1)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Moves carved pixels to a NumPy array with the same number of dimensions as the input
+    fn numpy_array<'py>(
+        &self,
+        py: Python<'py>,
+        data: Vec<u8>,
+        width: u32,
+        height: u32,
+    ) -> PyResult<Bound<'py, PyArrayDyn<u8>>> {
+        let (width, height) = (width as usize, height as usize);
+        let array = data.into_pyarray(py);
+        match self.channels {
+            None => array.reshape([height, width].as_slice()),
+            Some(channels) => array.reshape([height, width, channels].as_slice()),
+        }
+    }
+}
+
+/// The values of a NumPy array in row-major order, borrowed when they already are
+fn contiguous<'a, T: Copy>(array: ArrayViewD<'a, T>) -> std::borrow::Cow<'a, [T]> {
+    match array.to_slice() {
+        Some(slice) => slice.into(),
+        None => array.iter().copied().collect::<Vec<_>>().into(),
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "seamcarving")]
+fn seamcarving_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(resize, m)?)?;
+    m.add_function(wrap_pyfunction!(energy_map, m)?)?;
+    m.add_class::<PyCarvable>()?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+import numpy as np
+import pytest
+
+import seamcarving
+
+
+def noisy(height, width, channels=None):
+    shape = (height, width) if channels is None else (height, width, channels)
+    return np.random.default_rng(0).integers(0, 256, size=shape, dtype=np.uint8)
+
+
+def test_resize_keeps_dimensions_count():
+    assert seamcarving.resize(noisy(30, 40), 25, 20).shape == (20, 25)
+    assert seamcarving.resize(noisy(30, 40, 3), 25, 20).shape == (20, 25, 3)
+
+
+def test_resize_reads_non_contiguous_arrays():
+    img = noisy(30, 80, 3)
+    view = img[:, ::2]
+    assert np.array_equal(
+        seamcarving.resize(view, 30, 30),
+        seamcarving.resize(np.ascontiguousarray(view), 30, 30),
+    )
+
+
+def test_custom_energy_and_mask():
+    img = noisy(10, 8)
+    energy = np.full((10, 8), 100, dtype=np.uint32)
+    energy[:, 5] = 0
+    expected = np.delete(img, 5, axis=1)
+    assert np.array_equal(seamcarving.resize(img, 7, 10, energy=energy), expected)
+    assert np.array_equal(seamcarving.resize(img, 7, 10, energy=energy.astype(float)), expected)
+    mask = np.ones((10, 8), dtype=bool)
+    mask[:, 2] = False
+    assert np.array_equal(seamcarving.resize(img, 7, 10, mask=mask), np.delete(img, 2, axis=1))
+    with pytest.raises(ValueError):
+        seamcarving.resize(img, 7, 10, mask=mask[1:])
+
+
+def test_mask_outweighs_high_energies():
+    # The only unprotected pixel of row 5 is in a region where every pixel
+    # has a high gradient energy, while the protected pixels are flat
+    img = np.zeros((10, 8, 4), dtype=np.uint8)
+    y, x = np.mgrid[0:10, 0:8]
+    img[(x <= 4) & ((x // 2 + y // 2) % 2 == 1)] = 255
+    mask = np.zeros((10, 8), dtype=bool)
+    mask[5] = True
+    mask[5, 2] = False
+    seam = seamcarving.Carvable(img, mask=mask).remove_seam()
+    assert not any(mask[y, x] for x, y in seam)
+
+
+def test_energy_map():
+    energies = seamcarving.energy_map(np.zeros((4, 5, 3), dtype=np.uint8))
+    assert energies.dtype == np.uint32
+    assert np.array_equal(energies, np.zeros((4, 5)))
+
+
+def test_carvable():
+    img = noisy(12, 10, 4)
+    carvable = seamcarving.Carvable(img)
+    seam = carvable.remove_seam()
+    assert [y for _, y in seam] == list(range(12))
+    carvable.remove_seam()
+    assert carvable.seams[0] == seam
+    assert len(carvable.seams) == 2
+    assert np.array_equal(carvable.result(), seamcarving.resize(img, 8, 12))
+    while carvable.remove_seam() is not None:
+        pass
+    assert carvable.result().shape == (12, 0, 4)
@@ -0,0 +1,127 @@
+use core::convert::TryFrom;
+
+use crate::matrix::Matrix;
+use crate::pos::{Pos, Pos16};
+
+/// A matrix of positions in an original image, stored as `N` when all the positions
+/// of the image fit in it, and as `W` otherwise.
+///
+/// Carved views keep one alias per pixel, so storing them on 16 bits for images
+/// narrower than 65536 pixels halves their memory use.
+pub(crate) enum Aliases<N, W> {
+    Narrow(Matrix<N>),
+    Wide(Matrix<W>),
+}
+
+/// Columns, for views that only remove vertical seams
+pub(crate) type ColumnAliases = Aliases<u16, u32>;
+
+/// Positions, for views that remove seams in both directions
+pub(crate) type PosAliases = Aliases<Pos16, Pos>;
+
+/// Whether all the coordinates along a dimension of the given length fit on 16 bits
+pub(crate) fn fits_u16(length: u32) -> bool {
+    length <= u32::from(u16::MAX) + 1
+}
+
+impl<N, W> Aliases<N, W>
+where
+    N: Copy + Into<W> + TryFrom<W>,
+    W: Copy,
+{
+    /// Creates the matrix, narrow if `narrow` is true, in which case all values must fit in `N`
+    pub fn from_fn(size: Pos, narrow: bool, mut f: impl FnMut(usize, usize) -> W) -> Self {
+        if narrow {
+            Aliases::Narrow(Matrix::from_fn(size, |x, y| match N::try_from(f(x, y)) {
+                Ok(value) => value,
+                Err(_) => unreachable!("the value should fit in a narrow alias"),
+            }))
+        } else {
+            Aliases::Wide(Matrix::from_fn(size, f))
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, pos: Pos) -> W {
+        match self {
+            Aliases::Narrow(m) => m[pos].into(),
+            Aliases::Wide(m) => m[pos],
+        }
+    }
+
+    /// See [Matrix::get_pending]
+    #[inline(always)]
+    pub fn get_pending(&self, pos: Pos) -> W {
+        match self {
+            Aliases::Narrow(m) => (*m.get_pending(pos)).into(),
+            Aliases::Wide(m) => *m.get_pending(pos),
+        }
+    }
+
+    /// The current values of a row
+    #[inline]
+    pub fn row(&self, y: usize) -> impl Iterator<Item = W> + '_ {
+        let (narrow, wide) = match self {
+            Aliases::Narrow(m) => (m.row(y), &[][..]),
+            Aliases::Wide(m) => (&[][..], m.row(y)),
+        };
+        narrow.iter().map(|&n| n.into()).chain(wide.iter().copied())
+    }
+
+    pub fn remove_seam(&mut self, seam: &[Pos]) {
+        match self {
+            Aliases::Narrow(m) => m.remove_seam(seam),
+            Aliases::Wide(m) => m.remove_seam(seam),
+        }
+    }
+
+    pub fn defer_seam_removal(&mut self, seam: &[Pos]) {
+        match self {
+            Aliases::Narrow(m) => m.defer_seam_removal(seam),
+            Aliases::Wide(m) => m.defer_seam_removal(seam),
+        }
+    }
+
+    pub fn compact(&mut self) {
+        match self {
+            Aliases::Narrow(m) => m.compact(),
+            Aliases::Wide(m) => m.compact(),
+        }
+    }
+
+    pub fn restore_seam(&mut self, seam: &[Pos]) {
+        match self {
+            Aliases::Narrow(m) => m.restore_seam(seam),
+            Aliases::Wide(m) => m.restore_seam(seam),
+        }
+    }
+
+    pub fn remove_horizontal_seam(&mut self, seam: &[Pos]) {
+        match self {
+            Aliases::Narrow(m) => m.remove_horizontal_seam(seam),
+            Aliases::Wide(m) => m.remove_horizontal_seam(seam),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aliases::{fits_u16, ColumnAliases};
+    use crate::pos::Pos;
+
+    #[test]
+    fn narrow_and_wide_aliases_agree() {
+        let size = Pos(6, 3);
+        let mut narrow = ColumnAliases::from_fn(size, true, |x, _y| x as u32);
+        let mut wide = ColumnAliases::from_fn(size, false, |x, _y| x as u32);
+        let seam = [Pos(4, 2), Pos(3, 1), Pos(3, 0)];
+        narrow.remove_seam(&seam);
+        wide.remove_seam(&seam);
+        for y in 0..3 {
+            assert!(narrow.row(y).eq(wide.row(y)));
+        }
+        assert!(narrow.row(2).eq(vec![0, 1, 2, 3, 5]));
+        assert_eq!(narrow.get(Pos(3, 0)), 4);
+        assert!(fits_u16(65536) && !fits_u16(65537));
+    }
+}
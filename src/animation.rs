@@ -0,0 +1,269 @@
+use std::io::{Read, Write};
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::error::{DecodingError, EncodingError, ImageFormatHint};
+use image::{
+    AnimationDecoder, Delay, Frame, GenericImageView, ImageError, ImageFormat, ImageResult,
+    RgbaImage,
+};
+
+use crate::energy::energy_fn;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+use crate::{image_view_to_buffer, max_pos, Carved, Options, Rotated};
+
+/// A frame of an animated GIF or APNG file.
+///
+/// The buffer always contains the full composited image,
+/// with the dimensions of the whole animation.
+#[derive(Clone)]
+pub struct AnimationFrame {
+    /// The pixels of the frame
+    pub buffer: RgbaImage,
+    /// How long the frame is displayed
+    pub delay: Delay,
+    /// What happens to the frame when the next one is displayed, in the file it was read from.
+    /// Since the buffer is composited, it does not affect how the frames are written.
+    pub disposal: Disposal,
+}
+
+/// How the area covered by a frame is treated before the next frame is rendered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Disposal {
+    /// The frame is left in place
+    Keep,
+    /// The area of the frame is cleared
+    Background,
+    /// The area of the frame is restored to its previous contents
+    Previous,
+}
+
+/// Resizes all the frames of an animation to a lower width and height.
+///
+/// The seams are computed once, from the sum of the energies of all the frames,
+/// and the same seams are removed from every frame. This keeps the animation
+/// consistent: a pixel that does not move stays at the same place in every frame.
+/// Delays and disposal methods are kept.
+///
+/// ```no_run
+/// let file = std::fs::File::open("animation.gif").unwrap();
+/// let frames = seamcarving::read_gif(std::io::BufReader::new(file)).unwrap();
+/// let resized = seamcarving::resize_animation(&frames, 100, 100);
+/// let out = std::fs::File::create("resized.gif").unwrap();
+/// seamcarving::write_gif(out, &resized).unwrap();
+/// ```
+pub fn resize_animation(frames: &[AnimationFrame], width: u32, height: u32) -> Vec<AnimationFrame> {
+    let buffers: Vec<&RgbaImage> = frames.iter().map(|f| &f.buffer).collect();
+    let size = match buffers.first() {
+        Some(first) => max_pos(*first),
+        None => return vec![],
+    };
+    assert!(
+        buffers.iter().all(|&b| max_pos(b) == size),
+        "all the frames of an animation must have the same dimensions"
+    );
+    let Pos(to_remove_x, to_remove_y) = size - Pos(width, height);
+    let carved_x = carve_together(buffers, to_remove_x);
+    let rotated: Vec<_> = carved_x.iter().map(Rotated).collect();
+    let carved_y = carve_together(rotated.iter().collect(), to_remove_y);
+    carved_y
+        .iter()
+        .zip(frames)
+        .map(|(carved, frame)| AnimationFrame {
+            buffer: image_view_to_buffer(&Rotated(carved)),
+            delay: frame.delay,
+            disposal: frame.disposal,
+        })
+        .collect()
+}
+
+/// Removes the same seams from all the given images
+fn carve_together<IMG: GenericImageView>(frames: Vec<&IMG>, pixel_count: u32) -> Vec<Carved<'_, IMG>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut seam_finder = match frames.first() {
+        Some(&first) => SeamFinder::new(max_pos(first), &Options::default()),
+        None => return vec![],
+    };
+    let mut carved: Vec<_> = frames.into_iter().map(Carved::new).collect();
+    for _ in 0..pixel_count {
+        let seam = {
+            let carved = &carved;
+            seam_finder.extract_seam(|p| {
+                carved
+                    .iter()
+                    .map(|img| energy_fn(img, p))
+                    .fold(0, u32::saturating_add)
+            })
+        };
+        carved.iter_mut().for_each(|img| img.remove_seam(&seam));
+    }
+    carved
+}
+
+/// Reads all the frames of an animated GIF.
+pub fn read_gif<R: Read>(mut reader: R) -> ImageResult<Vec<AnimationFrame>> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    // The image crate composites the frames, but does not expose their disposal methods
+    let disposals = gif_disposals(&data)?;
+    let frames = GifDecoder::new(&data[..])?.into_frames().collect_frames()?;
+    Ok(with_disposals(frames, disposals))
+}
+
+fn gif_disposals(data: &[u8]) -> ImageResult<Vec<Disposal>> {
+    let mut decoder = gif::Decoder::new(data).map_err(gif_decoding_error)?;
+    let mut disposals = vec![];
+    while let Some(frame) = decoder.next_frame_info().map_err(gif_decoding_error)? {
+        disposals.push(match frame.dispose {
+            gif::DisposalMethod::Any | gif::DisposalMethod::Keep => Disposal::Keep,
+            gif::DisposalMethod::Background => Disposal::Background,
+            gif::DisposalMethod::Previous => Disposal::Previous,
+        });
+    }
+    Ok(disposals)
+}
+
+/// Writes frames as an infinitely looping animated GIF.
+///
+/// Colors are quantized to a 256 colors palette for each frame.
+/// Since the frames are composited, they are all written with the
+/// [Background](Disposal::Background) disposal method, whatever their `disposal`.
+pub fn write_gif<W: Write>(writer: W, frames: &[AnimationFrame]) -> ImageResult<()> {
+    let (width, height) = animation_dimensions(frames);
+    let (width, height) = (to_u16(width)?, to_u16(height)?);
+    let mut encoder = gif::Encoder::new(writer, width, height, &[]).map_err(gif_encoding_error)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(gif_encoding_error)?;
+    for frame in frames {
+        let mut pixels = frame.buffer.as_raw().clone();
+        let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+        let (numer, denom) = frame.delay.numer_denom_ms();
+        // GIF delays are in hundredths of a second
+        gif_frame.delay = to_u16(div_round(numer, denom.saturating_mul(10))).unwrap_or(u16::MAX);
+        // Frames are composited: each one replaces the whole canvas, which must be cleared
+        // so that its transparent pixels do not show the previous frame
+        gif_frame.dispose = gif::DisposalMethod::Background;
+        encoder.write_frame(&gif_frame).map_err(gif_encoding_error)?;
+    }
+    Ok(())
+}
+
+/// Reads all the frames of an animated PNG.
+///
+/// A PNG file that is not animated is returned as a single frame.
+pub fn read_apng<R: Read>(mut reader: R) -> ImageResult<Vec<AnimationFrame>> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    let decoder = PngDecoder::new(&data[..])?;
+    if !decoder.is_apng() {
+        let buffer = image::load_from_memory_with_format(&data, ImageFormat::Png)?.into_rgba8();
+        return Ok(vec![AnimationFrame {
+            buffer,
+            delay: Delay::from_numer_denom_ms(0, 1),
+            disposal: Disposal::Keep,
+        }]);
+    }
+    let disposals = apng_disposals(&data);
+    let frames = decoder.apng().into_frames().collect_frames()?;
+    Ok(with_disposals(frames, disposals))
+}
+
+/// Reads the dispose_op field of all the fcTL chunks of a PNG file
+fn apng_disposals(data: &[u8]) -> Vec<Disposal> {
+    const SIGNATURE_LEN: usize = 8;
+    const DISPOSE_OP_OFFSET: usize = 24;
+    let mut disposals = vec![];
+    let mut chunk = data.get(SIGNATURE_LEN..).unwrap_or(&[]);
+    while chunk.len() >= 8 {
+        let len = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as usize;
+        let (kind, contents) = (&chunk[4..8], &chunk[8..]);
+        if kind == b"fcTL" {
+            disposals.push(match contents.get(DISPOSE_OP_OFFSET) {
+                Some(1) => Disposal::Background,
+                Some(2) => Disposal::Previous,
+                _ => Disposal::Keep,
+            });
+        }
+        // Skip the chunk contents and its CRC
+        chunk = contents.get(len + 4..).unwrap_or(&[]);
+    }
+    disposals
+}
+
+/// Writes frames as an infinitely looping animated PNG.
+pub fn write_apng<W: Write>(writer: W, frames: &[AnimationFrame]) -> ImageResult<()> {
+    let (width, height) = animation_dimensions(frames);
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(png_encoding_error)?;
+    let mut writer = encoder.write_header().map_err(png_encoding_error)?;
+    for frame in frames {
+        let (numer, denom) = frame.delay.numer_denom_ms();
+        let delay_ms = to_u16(div_round(numer, denom)).unwrap_or(u16::MAX);
+        writer
+            .set_frame_delay(delay_ms, 1000)
+            .map_err(png_encoding_error)?;
+        writer
+            .set_dispose_op(match frame.disposal {
+                Disposal::Keep => png::DisposeOp::None,
+                Disposal::Background => png::DisposeOp::Background,
+                Disposal::Previous => png::DisposeOp::Previous,
+            })
+            .map_err(png_encoding_error)?;
+        // Frames are composited: they replace the whole canvas
+        writer
+            .set_blend_op(png::BlendOp::Source)
+            .map_err(png_encoding_error)?;
+        writer
+            .write_image_data(frame.buffer.as_raw())
+            .map_err(png_encoding_error)?;
+    }
+    writer.finish().map_err(png_encoding_error)
+}
+
+fn with_disposals(frames: Vec<Frame>, disposals: Vec<Disposal>) -> Vec<AnimationFrame> {
+    let disposals = disposals.into_iter().chain(std::iter::repeat(Disposal::Keep));
+    frames
+        .into_iter()
+        .zip(disposals)
+        .map(|(frame, disposal)| AnimationFrame {
+            delay: frame.delay(),
+            buffer: frame.into_buffer(),
+            disposal,
+        })
+        .collect()
+}
+
+fn animation_dimensions(frames: &[AnimationFrame]) -> (u32, u32) {
+    frames.first().map_or((0, 0), |f| f.buffer.dimensions())
+}
+
+fn div_round(numer: u32, denom: u32) -> u32 {
+    numer.saturating_add(denom / 2).checked_div(denom).unwrap_or(0)
+}
+
+fn to_u16(n: u32) -> ImageResult<u16> {
+    use std::convert::TryFrom;
+    u16::try_from(n).map_err(|e| {
+        ImageError::Encoding(EncodingError::new(ImageFormatHint::Unknown, e))
+    })
+}
+
+fn gif_decoding_error(err: gif::DecodingError) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormat::Gif.into(), err))
+}
+
+fn gif_encoding_error(err: gif::EncodingError) -> ImageError {
+    ImageError::Encoding(EncodingError::new(ImageFormat::Gif.into(), err))
+}
+
+fn png_encoding_error(err: png::EncodingError) -> ImageError {
+    ImageError::Encoding(EncodingError::new(ImageFormat::Png.into(), err))
+}
@@ -0,0 +1,130 @@
+use image::GenericImageView;
+
+use crate::pos::Pos;
+use crate::{carve_view, max_pos, resize_with_options, BufferOf, Options, Rotated};
+
+/// Resizes an image to the given aspect ratio (its width divided by its height),
+/// damaging its content as little as possible.
+///
+/// An image that is too wide can either lose columns or gain rows,
+/// and an image that is too tall can either lose rows or gain columns.
+/// The cost of the seams that would be removed on one axis is compared
+/// with the cost of the seams that would be duplicated on the other one,
+/// per unit of progress towards the ratio: inserting a row brings an image
+/// that is too wide as close to the ratio as removing `ratio` columns.
+/// The cheapest change is made first, until the image has the requested ratio.
+///
+/// An image can lose all but one of its columns or rows, and gain at most one less
+/// than it has on the other axis, so very different ratios cannot be reached:
+/// a 17x8 image becomes 1x15 for a ratio of 0.01, the closest it can get.
+///
+/// Returns the resized image and its dimensions, which tell how close to the ratio it is.
+///
+/// ```
+/// use image::GenericImageView;
+/// let img = image::GrayImage::from_fn(40, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let (resized, (width, height)) = seamcarving::resize_to_aspect(&img, 16. / 9.);
+/// assert_eq!(resized.dimensions(), (width, height));
+/// assert!((width as f64 / height as f64 - 16. / 9.).abs() < 0.2);
+/// ```
+///
+/// # Panics
+/// If `ratio` is not a positive finite number
+pub fn resize_to_aspect<IMG: GenericImageView>(img: &IMG, ratio: f64) -> (BufferOf<IMG>, (u32, u32))
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    assert!(ratio.is_finite() && ratio > 0., "The aspect ratio must be positive");
+    let Pos(width, height) = max_pos(img);
+    let (w, h) = (f64::from(width), f64::from(height));
+    let (width, height) = if w > ratio * h {
+        // Remove columns, or insert rows
+        let columns = width - ((ratio * h).round() as u32).max(1);
+        let rows = ((w / ratio).round() as u32).saturating_sub(height);
+        let (removed, inserted) = balance(
+            &seam_costs(img, columns),
+            &seam_costs(&Rotated(img), rows),
+            ratio,
+            |removed, inserted| w - removed as f64 <= ratio * (h + inserted as f64) + 0.5,
+        );
+        (width - removed, height + inserted)
+    } else {
+        // Remove rows, or insert columns
+        let rows = height - ((w / ratio).round() as u32).max(1).min(height);
+        let columns = ((ratio * h).round() as u32).saturating_sub(width);
+        let (removed, inserted) = balance(
+            &seam_costs(&Rotated(img), rows),
+            &seam_costs(img, columns),
+            1. / ratio,
+            |removed, inserted| h - removed as f64 <= (w + inserted as f64) / ratio + 0.5,
+        );
+        (width + inserted, height - removed)
+    };
+    let resized = resize_with_options(img, width, height, &Options::default());
+    (resized, (width, height))
+}
+
+/// The costs of the first `count` vertical seams that would be removed from an image.
+/// Seams duplicated to enlarge an image are the ones that would be removed first,
+/// so these are also the costs of inserting seams.
+fn seam_costs<IMG: GenericImageView>(img: &IMG, count: u32) -> Vec<u64>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(width, height) = max_pos(img);
+    let count = count.min(width.saturating_sub(1));
+    carve_view(img, width - count, height, &Options::default()).2
+}
+
+/// Decides how many seams to remove and to insert, by taking the cheapest of
+/// the next removal and the next insertion until `reached(removed, inserted)`.
+/// An insertion brings the image as close to the ratio as `insertion_progress` removals,
+/// so their costs are compared per removal.
+fn balance(
+    removals: &[u64],
+    insertions: &[u64],
+    insertion_progress: f64,
+    reached: impl Fn(usize, usize) -> bool,
+) -> (u32, u32) {
+    let (mut removed, mut inserted) = (0, 0);
+    while !reached(removed, inserted) {
+        match (removals.get(removed), insertions.get(inserted)) {
+            (Some(&removal), Some(&insertion))
+                if (insertion as f64) < removal as f64 * insertion_progress =>
+            {
+                inserted += 1
+            }
+            (Some(_), _) => removed += 1,
+            (None, Some(_)) => inserted += 1,
+            (None, None) => break,
+        }
+    }
+    (removed as u32, inserted as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::balance;
+
+    #[test]
+    fn balances_the_cheapest_changes() {
+        let removals = [1, 2, 5, 9];
+        let insertions = [3, 4, 10];
+        // Each change brings the image closer to the ratio by one
+        let reached = |needed: usize| move |r: usize, i: usize| r + i >= needed;
+        assert_eq!(balance(&removals, &insertions, 1., reached(0)), (0, 0));
+        assert_eq!(balance(&removals, &insertions, 1., reached(3)), (2, 1));
+        assert_eq!(balance(&removals, &insertions, 1., reached(5)), (3, 2));
+        assert_eq!(balance(&removals, &insertions, 1., reached(7)), (4, 3));
+        assert_eq!(balance(&removals, &insertions, 1., reached(9)), (4, 3));
+    }
+
+    #[test]
+    fn compares_costs_per_unit_of_progress() {
+        let (removals, insertions) = ([10, 10, 10], [15, 15, 15]);
+        // Each insertion does the work of two removals, for less than their cost
+        let reached = |r: usize, i: usize| r + 2 * i >= 4;
+        assert_eq!(balance(&removals, &insertions, 2., reached), (0, 2));
+        assert_eq!(balance(&removals, &insertions, 1., reached), (3, 1));
+    }
+}
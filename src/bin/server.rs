@@ -0,0 +1,276 @@
+//! An HTTP server that retargets images on the fly.
+//!
+//! Usage: `seamcarving-server [address] [root directory] [threads]`, which defaults to
+//! `seamcarving-server 127.0.0.1:8080 . <number of CPUs>`.
+//!
+//! - `GET /resize?src=photos/cat.jpg&w=300&h=200` resizes a file of the root directory,
+//! - `POST /resize?w=300&h=200` resizes the image sent in the request body.
+//!
+//! The optional `format` parameter selects the encoding of the response:
+//! `png` (the default), `jpeg`, `gif` or `bmp`.
+//! Requests are handled by a fixed number of threads, which limits the number of images
+//! carved at the same time.
+//!
+//! The vertical seams found for each source image are kept in memory, so that narrower
+//! versions of an image are carved by replaying them instead of searching new seams.
+//! Horizontal seams are searched in the narrowed image with [seamcarving::resize].
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use seamcarving::{replay, resize, resize_with_plan, CarvingPlan};
+
+/// Number of source images whose seams are kept in memory
+const CACHE_SIZE: usize = 64;
+/// Largest accepted request body
+const MAX_BODY: u64 = 64 << 20;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let address = args.get(1).map_or("127.0.0.1:8080", String::as_str);
+    let root = fs::canonicalize(args.get(2).map_or(".", String::as_str))?;
+    let threads: usize = match args.get(3) {
+        Some(threads) => threads.parse()?,
+        None => thread::available_parallelism().map_or(4, |n| n.get()),
+    };
+    let server = Arc::new(Server::http(address).map_err(|e| e.to_string())?);
+    // Tests read the port from this line when the address ends with :0
+    println!("Listening on http://{}", server.server_addr());
+    let state = Arc::new(State {
+        root,
+        cache: Mutex::new(SeamCache::default()),
+    });
+    let workers: Vec<_> = (0..threads.max(1))
+        .map(|_| {
+            let (server, state) = (Arc::clone(&server), Arc::clone(&state));
+            thread::spawn(move || {
+                while let Ok(request) = server.recv() {
+                    state.handle(request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("a worker panicked");
+    }
+    Ok(())
+}
+
+struct State {
+    root: PathBuf,
+    cache: Mutex<SeamCache>,
+}
+
+/// An error response
+struct HttpError(u16, String);
+
+impl<E: Error> From<E> for HttpError {
+    fn from(e: E) -> Self {
+        HttpError(500, e.to_string())
+    }
+}
+
+impl State {
+    fn handle(&self, mut request: Request) {
+        let response = match self.respond(&mut request) {
+            Ok((body, content_type)) => Response::from_data(body).with_header(content_type),
+            Err(HttpError(status, message)) => {
+                Response::from_data(message.into_bytes()).with_status_code(status)
+            }
+        };
+        // The client may have disconnected: there is nobody to report the error to
+        let _ = request.respond(response);
+    }
+
+    fn respond(&self, request: &mut Request) -> Result<(Vec<u8>, Header), HttpError> {
+        let (path, query) = match request.url().split_once('?') {
+            Some((path, query)) => (path, parse_query(query)),
+            None => (request.url(), HashMap::new()),
+        };
+        if path != "/resize" {
+            return Err(HttpError(404, format!("unknown path {}", path)));
+        }
+        let param = |name: &str| -> Result<u32, HttpError> {
+            let value = query.get(name).ok_or_else(|| bad_request(format!("missing {}", name)))?;
+            value.parse().map_err(|_| bad_request(format!("invalid {}", name)))
+        };
+        let (width, height) = (param("w")?, param("h")?);
+        let format = match query.get("format").map_or("png", String::as_str) {
+            "png" => ImageOutputFormat::Png,
+            "jpeg" | "jpg" => ImageOutputFormat::Jpeg(90),
+            "gif" => ImageOutputFormat::Gif,
+            "bmp" => ImageOutputFormat::Bmp,
+            other => return Err(bad_request(format!("unsupported format {}", other))),
+        };
+        let bytes = match (request.method(), query.get("src")) {
+            (Method::Get, Some(src)) => fs::read(self.source_path(src)?)
+                .map_err(|e| HttpError(404, format!("cannot read {}: {}", src, e)))?,
+            (Method::Get, None) => return Err(bad_request("missing src".into())),
+            (Method::Post, _) => {
+                let mut body = Vec::new();
+                request.as_reader().take(MAX_BODY).read_to_end(&mut body)?;
+                body
+            }
+            _ => return Err(HttpError(405, "use GET or POST".into())),
+        };
+        let img = image::load_from_memory(&bytes)
+            .map_err(|e| HttpError(415, format!("cannot decode the image: {}", e)))?;
+        let resized = self.resize(&img, source_key(&bytes), width, height)?;
+        let content_type = match format {
+            ImageOutputFormat::Jpeg(_) => "image/jpeg",
+            ImageOutputFormat::Gif => "image/gif",
+            ImageOutputFormat::Bmp => "image/bmp",
+            _ => "image/png",
+        };
+        let resized = match format {
+            // JPEG has no alpha channel
+            ImageOutputFormat::Jpeg(_) => DynamicImage::ImageRgb8(resized.to_rgb8()),
+            _ => resized,
+        };
+        let mut body = Vec::new();
+        resized.write_to(&mut body, format)?;
+        let header = Header::from_bytes("Content-Type", content_type).expect("valid header");
+        Ok((body, header))
+    }
+
+    /// The path of a source image, which must be in the root directory
+    fn source_path(&self, src: &str) -> Result<PathBuf, HttpError> {
+        let path = fs::canonicalize(self.root.join(src))
+            .map_err(|e| HttpError(404, format!("cannot read {}: {}", src, e)))?;
+        if !path.starts_with(&self.root) {
+            return Err(HttpError(403, format!("{} is outside the served directory", src)));
+        }
+        Ok(path)
+    }
+
+    /// Removes vertical seams by replaying the ones found for the same image before when
+    /// there are enough, and then horizontal seams
+    fn resize(
+        &self,
+        img: &DynamicImage,
+        key: u64,
+        width: u32,
+        height: u32,
+    ) -> Result<DynamicImage, HttpError> {
+        let (original_width, original_height) = img.dimensions();
+        let count = original_width.saturating_sub(width) as usize;
+        let cached = self.cache.lock().expect("poisoned cache").get(key, count);
+        let narrow = match cached {
+            Some(plan) => replay(img, &plan)?,
+            None => {
+                let (narrow, plan) =
+                    resize_with_plan(img, width, original_height, &Default::default());
+                self.cache.lock().expect("poisoned cache").insert(key, plan);
+                narrow
+            }
+        };
+        if height >= original_height {
+            return Ok(DynamicImage::ImageRgba8(narrow));
+        }
+        Ok(DynamicImage::ImageRgba8(resize(&narrow, narrow.width(), height)))
+    }
+}
+
+/// The vertical seams found for recently requested images, identified by their contents.
+/// When it is full, the least recently requested image is evicted.
+#[derive(Default)]
+struct SeamCache {
+    plans: HashMap<u64, CarvingPlan>,
+    // Keys from the least to the most recently used
+    order: VecDeque<u64>,
+}
+
+impl SeamCache {
+    /// The first `count` seams of an image, if they were found before
+    fn get(&mut self, key: u64, count: usize) -> Option<CarvingPlan> {
+        self.touch(key);
+        let plan = self.plans.get(&key)?;
+        if plan.seams.len() < count {
+            return None;
+        }
+        Some(CarvingPlan {
+            width: plan.width,
+            height: plan.height,
+            seams: plan.seams[..count].to_vec(),
+        })
+    }
+
+    /// Stores the seams of an image, replacing shorter lists of seams.
+    /// Seams are removed one at a time, so a shorter list is a prefix of a longer one.
+    fn insert(&mut self, key: u64, plan: CarvingPlan) {
+        if let Some(existing) = self.plans.get_mut(&key) {
+            if existing.seams.len() < plan.seams.len() {
+                *existing = plan;
+            }
+            self.touch(key);
+            return;
+        }
+        if self.order.len() == CACHE_SIZE {
+            let oldest = self.order.pop_front().expect("the cache is full");
+            self.plans.remove(&oldest);
+        }
+        self.order.push_back(key);
+        self.plans.insert(key, plan);
+    }
+
+    /// Marks a cached image as the most recently used one
+    fn touch(&mut self, key: u64) {
+        if let Some(index) = self.order.iter().position(|&k| k == key) {
+            self.order.remove(index);
+            self.order.push_back(key);
+        }
+    }
+}
+
+fn source_key(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn bad_request(message: String) -> HttpError {
+    HttpError(400, message)
+}
+
+/// Decodes the parameters of a query string
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (percent_decode(name), percent_decode(value)))
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
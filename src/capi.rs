@@ -0,0 +1,225 @@
+//! A C interface, enabled with the `capi` feature.
+//!
+//! The declarations are in `include/seamcarving.h`, generated by cbindgen when building
+//! with the feature. Images are 8-bit RGBA, stored row by row, with `stride` bytes
+//! between the starts of two consecutive rows.
+//! Functions return a [SeamcarvingStatus] instead of panicking across the boundary.
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use image::{GenericImageView, RgbaImage};
+
+use crate::raw::{resize_raw, RawImage};
+use crate::{Carvable, Options};
+
+/// The result of a function of the C interface
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeamcarvingStatus {
+    Ok = 0,
+    /// A required pointer was null
+    NullPointer = 1,
+    /// The stride is smaller than a row of pixels
+    InvalidStride = 2,
+    /// The target size is larger than the image
+    InvalidSize = 3,
+    /// The image has no seam left to remove
+    NoSeamLeft = 4,
+    /// An unexpected internal error
+    Panic = 5,
+}
+
+/// An image from which vertical seams can be removed one by one, like [Carvable]
+pub struct SeamcarvingCarvable {
+    carvable: Carvable<'static, RgbaImage>,
+    // Owned by the handle: the carvable borrows it until it is freed
+    image: *mut RgbaImage,
+}
+
+/// Resizes an RGBA image to a lower width and height.
+///
+/// `out` must have room for `target_width * target_height * 4` bytes,
+/// and receives the resized image without padding between rows.
+///
+/// # Safety
+/// `data` must point to `(height - 1) * stride + width * 4` readable bytes,
+/// and `out` to `target_width * target_height * 4` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn seamcarving_resize_rgba8(
+    data: *const u8,
+    width: u32,
+    height: u32,
+    stride: usize,
+    target_width: u32,
+    target_height: u32,
+    out: *mut u8,
+) -> SeamcarvingStatus {
+    if out.is_null() {
+        return SeamcarvingStatus::NullPointer;
+    }
+    if target_width > width || target_height > height {
+        return SeamcarvingStatus::InvalidSize;
+    }
+    let img = match raw_rgba8(data, width, height, stride) {
+        Ok(img) => img,
+        Err(status) => return status,
+    };
+    guard(|| {
+        let (carved, _) = resize_raw(&img, target_width, target_height, &Options::default());
+        ptr::copy_nonoverlapping(carved.as_ptr(), out, carved.len());
+        SeamcarvingStatus::Ok
+    })
+}
+
+/// Copies an RGBA image into a new [SeamcarvingCarvable], stored in `*carvable`.
+/// It must be freed with [seamcarving_carvable_free].
+///
+/// # Safety
+/// `data` must point to `(height - 1) * stride + width * 4` readable bytes,
+/// and `carvable` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn seamcarving_carvable_new(
+    data: *const u8,
+    width: u32,
+    height: u32,
+    stride: usize,
+    carvable: *mut *mut SeamcarvingCarvable,
+) -> SeamcarvingStatus {
+    if carvable.is_null() {
+        return SeamcarvingStatus::NullPointer;
+    }
+    let img = match raw_rgba8(data, width, height, stride) {
+        Ok(img) => img,
+        Err(status) => return status,
+    };
+    guard(|| {
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            let p = img.pixel(x, y);
+            image::Rgba([p[0], p[1], p[2], p[3]])
+        });
+        let image = Box::into_raw(Box::new(image));
+        let handle = SeamcarvingCarvable {
+            carvable: Carvable::new(&*image),
+            image,
+        };
+        *carvable = Box::into_raw(Box::new(handle));
+        SeamcarvingStatus::Ok
+    })
+}
+
+/// Removes a vertical seam, diminishing the width of the image by 1
+///
+/// # Safety
+/// `carvable` must come from [seamcarving_carvable_new] and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn seamcarving_carvable_remove_seam(
+    carvable: *mut SeamcarvingCarvable,
+) -> SeamcarvingStatus {
+    let handle = match carvable.as_mut() {
+        Some(handle) => handle,
+        None => return SeamcarvingStatus::NullPointer,
+    };
+    let (width, height) = handle.carvable.result().dimensions();
+    if width == 0 || height == 0 {
+        return SeamcarvingStatus::NoSeamLeft;
+    }
+    guard(|| {
+        handle.carvable.remove_seam();
+        SeamcarvingStatus::Ok
+    })
+}
+
+/// Stores the current size of the image in `*width` and `*height`
+///
+/// # Safety
+/// `carvable` must come from [seamcarving_carvable_new] and not be freed,
+/// and `width` and `height` must be valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn seamcarving_carvable_dimensions(
+    carvable: *const SeamcarvingCarvable,
+    width: *mut u32,
+    height: *mut u32,
+) -> SeamcarvingStatus {
+    match (carvable.as_ref(), width.as_mut(), height.as_mut()) {
+        (Some(handle), Some(width), Some(height)) => {
+            let result = handle.carvable.result();
+            *width = result.width();
+            *height = result.height();
+            SeamcarvingStatus::Ok
+        }
+        _ => SeamcarvingStatus::NullPointer,
+    }
+}
+
+/// Copies the current image to `out`, with `stride` bytes between the starts of two rows
+///
+/// # Safety
+/// `carvable` must come from [seamcarving_carvable_new] and not be freed,
+/// and `out` must point to `(height - 1) * stride + width * 4` writable bytes,
+/// for the size given by [seamcarving_carvable_dimensions].
+#[no_mangle]
+pub unsafe extern "C" fn seamcarving_carvable_get_result(
+    carvable: *const SeamcarvingCarvable,
+    out: *mut u8,
+    stride: usize,
+) -> SeamcarvingStatus {
+    let handle = match carvable.as_ref() {
+        Some(handle) if !out.is_null() => handle,
+        _ => return SeamcarvingStatus::NullPointer,
+    };
+    let result = handle.carvable.result();
+    let row_len = result.width() as usize * 4;
+    if stride < row_len {
+        return SeamcarvingStatus::InvalidStride;
+    }
+    guard(|| {
+        let mut buffer = RgbaImage::new(result.width(), result.height());
+        result.copy_into(&mut buffer);
+        for (y, row) in buffer.chunks_exact(row_len.max(1)).enumerate() {
+            ptr::copy_nonoverlapping(row.as_ptr(), out.add(y * stride), row.len());
+        }
+        SeamcarvingStatus::Ok
+    })
+}
+
+/// Frees a [SeamcarvingCarvable]. Does nothing if `carvable` is null.
+///
+/// # Safety
+/// `carvable` must come from [seamcarving_carvable_new] and not be freed already.
+#[no_mangle]
+pub unsafe extern "C" fn seamcarving_carvable_free(carvable: *mut SeamcarvingCarvable) {
+    if carvable.is_null() {
+        return;
+    }
+    let SeamcarvingCarvable { carvable, image } = *Box::from_raw(carvable);
+    drop(carvable);
+    drop(Box::from_raw(image));
+}
+
+/// Checks the layout of an RGBA image given by the caller
+unsafe fn raw_rgba8<'a>(
+    data: *const u8,
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> Result<RawImage<'a, u8>, SeamcarvingStatus> {
+    if data.is_null() {
+        return Err(SeamcarvingStatus::NullPointer);
+    }
+    let row_len = width as usize * 4;
+    if stride < row_len {
+        return Err(SeamcarvingStatus::InvalidStride);
+    }
+    let len = match height {
+        0 => 0,
+        h => (h as usize - 1) * stride + row_len,
+    };
+    let data = slice::from_raw_parts(data, len);
+    RawImage::new(data, width, height, stride, 4).map_err(|_| SeamcarvingStatus::InvalidStride)
+}
+
+/// Runs `f`, turning panics into an error code
+fn guard(f: impl FnOnce() -> SeamcarvingStatus) -> SeamcarvingStatus {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(SeamcarvingStatus::Panic)
+}
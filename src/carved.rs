@@ -1,6 +1,8 @@
+use std::ops::Deref;
+
 use image::{GenericImageView, ImageBuffer, Pixel};
 
-use crate::matrix::Matrix;
+use crate::aliases::{fits_u16, ColumnAliases};
 use crate::pos::Pos;
 use crate::{image_view_to_buffer, max_pos};
 
@@ -11,13 +13,13 @@ pub struct Carved<'a, IMG: GenericImageView> {
     img: &'a IMG,
     removed: u32,
     // pos_aliases is a matrix such as img[x,y] = self[pos_aliases[x,y],y]
-    pos_aliases: Matrix<u32>,
+    pos_aliases: ColumnAliases,
 }
 
 impl<'a, IMG: GenericImageView> Carved<'a, IMG> {
     pub(crate) fn new(img: &'a IMG) -> Self {
         let size = max_pos(img);
-        let pos_aliases = Matrix::from_fn(size, |x, _y| x as u32);
+        let pos_aliases = ColumnAliases::from_fn(size, fits_u16(size.0), |x, _y| x as u32);
         Carved {
             img,
             removed: 0,
@@ -28,15 +30,119 @@ impl<'a, IMG: GenericImageView> Carved<'a, IMG> {
         self.pos_aliases.remove_seam(seam);
         self.removed += 1;
     }
+    /// Removes a seam, deferring the update of the aliases until [compact](Carved::compact)
+    pub(crate) fn defer_seam_removal(&mut self, seam: &[Pos]) {
+        self.pos_aliases.defer_seam_removal(seam);
+        self.removed += 1;
+    }
+    /// Applies deferred seam removals
+    pub(crate) fn compact(&mut self) {
+        self.pos_aliases.compact();
+    }
+    /// Puts back the last removed seam
+    pub(crate) fn restore_seam(&mut self, seam: &[Pos]) {
+        self.pos_aliases.restore_seam(seam);
+        self.removed -= 1;
+    }
+    /// The image from which seams are removed
+    pub(crate) fn original(&self) -> &'a IMG {
+        self.img
+    }
     /// Given a position in the carved image, return a position in the original
     #[inline(always)]
-    fn transform_pos(&self, pos: Pos) -> Pos {
+    pub(crate) fn transform_pos(&self, pos: Pos) -> Pos {
         let mut pos = pos;
-        pos.0 = self.pos_aliases[pos];
+        pos.0 = self.pos_aliases.get(pos);
         pos
     }
 }
 
+impl<'a, P, C> Carved<'a, ImageBuffer<P, C>>
+where
+    P: Pixel + 'static,
+    C: Deref<Target = [P::Subpixel]>,
+{
+    /// Writes the carved image to an existing buffer, reusing its allocation.
+    ///
+    /// Runs of pixels that are contiguous in the original image are copied at once,
+    /// which is much faster than converting the image with
+    /// [image_view_to_buffer](crate::image_view_to_buffer).
+    pub fn copy_into(&self, out: &mut ImageBuffer<P, Vec<P::Subpixel>>) {
+        let (width, height) = self.dimensions();
+        let original_width = self.img.width() as usize;
+        let indices = (0..height as usize).flat_map(|y| {
+            let row = self.pos_aliases.row(y);
+            row.map(move |x| y * original_width + x as usize)
+        });
+        copy_runs(self.img, width, height, indices, out);
+    }
+}
+
+/// Fills `out` with the pixels of `src` at the given indices,
+/// copying consecutive indices as a single slice.
+pub(crate) fn copy_runs<P, C>(
+    src: &ImageBuffer<P, C>,
+    width: u32,
+    height: u32,
+    indices: impl Iterator<Item = usize>,
+    out: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+) where
+    P: Pixel + 'static,
+    C: Deref<Target = [P::Subpixel]>,
+{
+    let channels = usize::from(P::CHANNEL_COUNT);
+    let raw: &[P::Subpixel] = src;
+    let mut data = std::mem::take(out).into_raw();
+    data.clear();
+    data.reserve(width as usize * height as usize * channels);
+    let mut run: Option<(usize, usize)> = None;
+    for i in indices {
+        run = match run {
+            Some((start, end)) if end == i => Some((start, i + 1)),
+            Some((start, end)) => {
+                data.extend_from_slice(&raw[start * channels..end * channels]);
+                Some((i, i + 1))
+            }
+            None => Some((i, i + 1)),
+        };
+    }
+    if let Some((start, end)) = run {
+        data.extend_from_slice(&raw[start * channels..end * channels]);
+    }
+    *out = ImageBuffer::from_raw(width, height, data).expect("all pixels should have been copied");
+}
+
+/// A view of a [Carved] image that takes its deferred seam removals into account.
+///
+/// [Carved] itself ignores them, because checking for them would slow down every access.
+pub(crate) struct PendingCarved<'b, 'a, IMG: GenericImageView>(pub &'b Carved<'a, IMG>);
+
+impl<'b, 'a, IMG: GenericImageView> GenericImageView for PendingCarved<'b, 'a, IMG> {
+    type Pixel = IMG::Pixel;
+    type InnerImageView = IMG::InnerImageView;
+
+    #[inline(always)]
+    fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    #[inline(always)]
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        self.0.bounds()
+    }
+
+    #[inline(always)]
+    fn get_pixel(&self, x: u32, y: u32) -> Self::Pixel {
+        let u = self.0.pos_aliases.get_pending(Pos(x, y));
+        self.0.img.get_pixel(u, y)
+    }
+
+    fn inner(&self) -> &Self::InnerImageView {
+        self.0.img.inner()
+    }
+}
+
+#[allow(clippy::from_over_into)]
 impl<'a, 'b, IMG: GenericImageView>
     Into<ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>>
     for &'b Carved<'a, IMG>
@@ -0,0 +1,145 @@
+use std::ops::Deref;
+
+use image::{GenericImageView, ImageBuffer, Pixel};
+
+use crate::carved::copy_runs;
+use crate::aliases::{fits_u16, PosAliases};
+use crate::max_pos;
+use crate::plan::{Axis, Seam};
+use crate::pos::Pos;
+
+/// An image with some vertical and horizontal seams carved.
+///
+/// Contrary to nesting [Carved](crate::Carved) and [Rotated](crate::Rotated) views,
+/// reading a pixel takes a single lookup, whatever the number of carved seams.
+/// Use [image_view_to_buffer](crate::image_view_to_buffer) to store the result in a buffer.
+pub struct Carved2D<'a, IMG: GenericImageView> {
+    img: &'a IMG,
+    size: Pos,
+    // pos_aliases is a matrix such as self[x,y] = img[pos_aliases[x,y]]
+    pos_aliases: PosAliases,
+}
+
+impl<'a, IMG: GenericImageView> Carved2D<'a, IMG> {
+    pub(crate) fn new(img: &'a IMG) -> Self {
+        let size = max_pos(img);
+        let narrow = fits_u16(size.0) && fits_u16(size.1);
+        let pos_aliases = PosAliases::from_fn(size, narrow, |x, y| Pos(x as u32, y as u32));
+        Carved2D {
+            img,
+            size,
+            pos_aliases,
+        }
+    }
+    /// Removes a vertical seam, given from the bottom row to the top one
+    pub(crate) fn remove_vertical_seam(&mut self, seam: &[Pos]) {
+        self.pos_aliases.remove_seam(seam);
+        self.size.0 -= 1;
+    }
+    /// Removes a vertical seam, deferring the update of the aliases until [compact](Carved2D::compact)
+    pub(crate) fn defer_vertical_seam_removal(&mut self, seam: &[Pos]) {
+        self.pos_aliases.defer_seam_removal(seam);
+        self.size.0 -= 1;
+    }
+    /// Applies deferred seam removals
+    pub(crate) fn compact(&mut self) {
+        self.pos_aliases.compact();
+    }
+    /// Removes a horizontal seam, given in the coordinates of the [Rotated](crate::Rotated) view,
+    /// from the last column to the first one
+    pub(crate) fn remove_horizontal_seam(&mut self, seam: &[Pos]) {
+        self.pos_aliases.remove_horizontal_seam(seam);
+        self.size.1 -= 1;
+    }
+    /// Removes a seam of a [CarvingPlan](crate::CarvingPlan)
+    pub(crate) fn remove(&mut self, seam: &Seam) {
+        let positions = seam.positions();
+        match seam.axis {
+            Axis::Vertical => self.remove_vertical_seam(&positions),
+            Axis::Horizontal => self.remove_horizontal_seam(&positions),
+        }
+    }
+    /// The image from which seams are removed
+    pub fn original(&self) -> &'a IMG {
+        self.img
+    }
+    /// The position in the original image of a pixel of the carved view
+    pub(crate) fn transform_pos(&self, pos: Pos) -> Pos {
+        self.pos_aliases.get(pos)
+    }
+}
+
+impl<'a, P, C> Carved2D<'a, ImageBuffer<P, C>>
+where
+    P: Pixel + 'static,
+    C: Deref<Target = [P::Subpixel]>,
+{
+    /// Writes the carved image to an existing buffer, reusing its allocation.
+    ///
+    /// See [Carved::copy_into](crate::Carved::copy_into).
+    pub fn copy_into(&self, out: &mut ImageBuffer<P, Vec<P::Subpixel>>) {
+        let Pos(width, height) = self.size;
+        let original_width = self.img.width() as usize;
+        let indices = (0..height as usize).flat_map(|y| {
+            let row = self.pos_aliases.row(y);
+            row.map(move |Pos(u, v)| v as usize * original_width + u as usize)
+        });
+        copy_runs(self.img, width, height, indices, out);
+    }
+}
+
+/// A view of a [Carved2D] image that takes its deferred seam removals into account
+pub(crate) struct PendingCarved2D<'b, 'a, IMG: GenericImageView>(pub &'b Carved2D<'a, IMG>);
+
+impl<'b, 'a, IMG: GenericImageView> GenericImageView for PendingCarved2D<'b, 'a, IMG> {
+    type Pixel = IMG::Pixel;
+    type InnerImageView = IMG::InnerImageView;
+
+    #[inline(always)]
+    fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    #[inline(always)]
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        self.0.bounds()
+    }
+
+    #[inline(always)]
+    fn get_pixel(&self, x: u32, y: u32) -> Self::Pixel {
+        let Pos(u, v) = self.0.pos_aliases.get_pending(Pos(x, y));
+        self.0.img.get_pixel(u, v)
+    }
+
+    fn inner(&self) -> &Self::InnerImageView {
+        self.0.img.inner()
+    }
+}
+
+impl<'a, IMG: GenericImageView> GenericImageView for Carved2D<'a, IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'a,
+{
+    type Pixel = IMG::Pixel;
+    type InnerImageView = IMG::InnerImageView;
+
+    #[inline(always)]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.size.0, self.size.1)
+    }
+
+    #[inline(always)]
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        (0, 0, self.size.0, self.size.1)
+    }
+
+    #[inline(always)]
+    fn get_pixel(&self, x: u32, y: u32) -> Self::Pixel {
+        let Pos(u, v) = self.pos_aliases.get(Pos(x, y));
+        self.img.get_pixel(u, v)
+    }
+
+    fn inner(&self) -> &Self::InnerImageView {
+        self.img.inner()
+    }
+}
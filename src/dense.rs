@@ -0,0 +1,296 @@
+use std::ops::Range;
+
+use image::{GenericImageView, Pixel};
+use num_traits::{Bounded, ToPrimitive};
+
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::max_energy;
+use crate::{max_pos, Options, Solver, TieBreak};
+
+/// Finds `count` vertical seams to remove from an image, like
+/// [SeamFinder](crate::seam_finder::SeamFinder) would, for images of `u8` pixels
+/// with 1, 3 or 4 channels.
+///
+/// The pixels are copied to a contiguous buffer, and the costs are stored in dense rows
+/// instead of `Option`s, so that the computation of a row vectorizes.
+/// Returns `None` for other pixel types, and for options only the generic path supports.
+pub(crate) fn find_seams<IMG: GenericImageView>(
+    img: &IMG,
+    count: u32,
+    options: &Options,
+) -> Option<Vec<Vec<Pos>>> {
+    let supported = options.solver == Solver::DynamicProgramming
+        && options.tie_break == TieBreak::Leftmost
+        && options.max_step == 1
+        && options.seams_per_pass == 1
+        && options.pyramid_factor == 1;
+    // u8 is the only subpixel type with this range
+    let max = <<IMG::Pixel as Pixel>::Subpixel as Bounded>::max_value().to_f64();
+    let min = <<IMG::Pixel as Pixel>::Subpixel as Bounded>::min_value().to_f64();
+    if !supported || max != Some(255.) || min != Some(0.) {
+        return None;
+    }
+    let seams = match <IMG::Pixel as Pixel>::CHANNEL_COUNT {
+        1 => DenseSeamFinder::<1>::new(img).extract_seams(count),
+        3 => DenseSeamFinder::<3>::new(img).extract_seams(count),
+        4 => DenseSeamFinder::<4>::new(img).extract_seams(count),
+        _ => return None,
+    };
+    Some(seams)
+}
+
+/// A dirty range that contains no position, and grows to the first one added
+const CLEAN: Range<u32> = Range {
+    start: u32::MAX,
+    end: 0,
+};
+
+struct DenseSeamFinder<const N: usize> {
+    size: Pos,
+    // The channels of the pixels of the carved image
+    pixels: Matrix<[u8; N]>,
+    // The energy of the best seam ending at each position
+    costs: Matrix<u32>,
+    // The horizontal offset of the predecessor of each position on its best seam
+    predecessor_dx: Matrix<i8>,
+    // Whether the cost and predecessor of each position are up to date.
+    // This takes a byte per position rather than a bit: its rows move with every removed seam
+    // like the rows of the other matrices, and fill updates it in the same loop as the costs,
+    // which only vectorizes when every position has its own byte.
+    valid: Matrix<bool>,
+    // For each row, a range that contains all the positions that are not valid
+    dirty: Vec<Range<u32>>,
+    // Vector used during invalid position clearing
+    to_clear: Vec<Pos>,
+    // Values computed for the dirty range of a row, before keeping the ones of invalid positions
+    energies: Vec<u32>,
+    new_costs: Vec<u32>,
+    new_dx: Vec<i8>,
+}
+
+impl<const N: usize> DenseSeamFinder<N> {
+    fn new<IMG: GenericImageView>(img: &IMG) -> Self {
+        let size = max_pos(img);
+        let pixels = Matrix::from_fn(size, |x, y| {
+            let mut channels = [0; N];
+            let pixel = img.get_pixel(x as u32, y as u32);
+            for (c, &value) in channels.iter_mut().zip(pixel.channels()) {
+                *c = value.to_u8().unwrap_or(0);
+            }
+            channels
+        });
+        DenseSeamFinder {
+            size,
+            pixels,
+            costs: Matrix::from_fn(size, |_, _| 0),
+            predecessor_dx: Matrix::from_fn(size, |_, _| 0),
+            valid: Matrix::from_fn(size, |_, _| false),
+            dirty: vec![0..size.0; size.1 as usize],
+            to_clear: Vec::with_capacity(size.1 as usize),
+            energies: Vec::with_capacity(size.0 as usize),
+            new_costs: Vec::with_capacity(size.0 as usize),
+            new_dx: Vec::with_capacity(size.0 as usize),
+        }
+    }
+
+    fn extract_seams(mut self, count: u32) -> Vec<Vec<Pos>> {
+        (0..count).map(|_| self.extract_seam()).collect()
+    }
+
+    fn extract_seam(&mut self) -> Vec<Pos> {
+        self.fill();
+        let Pos(width, height) = self.size;
+        let mut seam = Vec::with_capacity(height as usize);
+        if let Some(bottom) = height.checked_sub(1) {
+            // min_by_key keeps the leftmost of the lowest-energy positions
+            let costs = self.costs.row(bottom as usize);
+            let mut next = (0..width)
+                .min_by_key(|&x| costs[x as usize])
+                .map(|x| Pos(x, bottom));
+            while let Some(pos) = next {
+                assert!(self.valid[pos], "should be filled");
+                next = if pos.1 == 0 {
+                    None
+                } else {
+                    Some(self.predecessor(pos))
+                };
+                self.clear(pos);
+                seam.push(pos);
+            }
+        }
+        self.clear_crossing(&seam);
+        self.size.0 -= 1;
+        self.pixels.remove_seam(&seam);
+        self.costs.remove_seam(&seam);
+        self.predecessor_dx.remove_seam(&seam);
+        self.valid.remove_seam(&seam);
+        seam
+    }
+
+    #[inline(always)]
+    fn predecessor(&self, pos: Pos) -> Pos {
+        let dx = self.predecessor_dx[pos];
+        Pos(pos.0.wrapping_add(dx as u32), pos.1 - 1)
+    }
+
+    /// Recursively invalidates a position and the positions whose best seam goes through it
+    fn clear(&mut self, p: Pos) {
+        self.to_clear.push(p);
+        while let Some(pos) = self.to_clear.pop() {
+            self.valid[pos] = false;
+            let dirty = &mut self.dirty[pos.1 as usize];
+            dirty.start = dirty.start.min(pos.0);
+            dirty.end = dirty.end.max(pos.0 + 1);
+            for s in pos.successors(self.size, 1) {
+                if self.valid[s] && self.predecessor(s) == pos {
+                    self.to_clear.push(s)
+                }
+            }
+        }
+    }
+
+    /// Invalidates the positions whose predecessor is on the other side of a seam
+    /// that is about to be removed, like
+    /// [SeamFinder::clear_crossing](crate::seam_finder::SeamFinder::clear_crossing)
+    fn clear_crossing(&mut self, seam: &[Pos]) {
+        for pair in seam.windows(2) {
+            let (Pos(x, y), Pos(above, _)) = (pair[0], pair[1]);
+            let end = (x + 4).min(self.size.0);
+            for pos in (x.saturating_sub(3)..end).map(|x| Pos(x, y)) {
+                if self.valid[pos] && (pos.0 > x) != (self.predecessor(pos).0 > above) {
+                    self.clear(pos);
+                }
+            }
+        }
+    }
+
+    /// Computes the costs of all the invalid positions, row by row
+    fn fill(&mut self) {
+        let Pos(width, height) = self.size;
+        for y in 0..height as usize {
+            let dirty = self.dirty[y].start..self.dirty[y].end.min(width);
+            self.dirty[y] = CLEAN;
+            if dirty.start >= dirty.end {
+                continue;
+            }
+            let span = dirty.start as usize..dirty.end as usize;
+            self.compute_energies(y, span.clone());
+            self.new_costs.resize(span.len(), 0);
+            self.new_dx.resize(span.len(), 0);
+            if y == 0 {
+                self.new_costs.copy_from_slice(&self.energies);
+                self.new_dx.iter_mut().for_each(|dx| *dx = 0);
+            } else {
+                self.compute_costs(y, span.clone());
+            }
+            // Valid positions keep their cost, like in the generic path
+            let costs = &mut self.costs.row_mut(y)[span.clone()];
+            let dxs = &mut self.predecessor_dx.row_mut(y)[span.clone()];
+            let valid = &mut self.valid.row_mut(y)[span];
+            let new_values = self.new_costs.iter().zip(&self.new_dx);
+            for (((cost, dx), valid), (&new_cost, &new_dx)) in
+                costs.iter_mut().zip(dxs).zip(valid).zip(new_values)
+            {
+                *cost = if *valid { *cost } else { new_cost };
+                *dx = if *valid { *dx } else { new_dx };
+                *valid = true;
+            }
+        }
+    }
+
+    /// Computes the energy of the pixels of a range of a row in `energies`
+    fn compute_energies(&mut self, y: usize, span: Range<usize>) {
+        let last_y = self.size.1 as usize - 1;
+        let top = self.pixels.row(y.saturating_sub(1));
+        let row = self.pixels.row(y);
+        let bottom = self.pixels.row((y + 1).min(last_y));
+        let last_x = row.len() - 1;
+        let energy = |x: usize| {
+            let (left, right) = (x.saturating_sub(1), (x + 1).min(last_x));
+            square_diff(&top[x], &bottom[x]) + square_diff(&row[left], &row[right])
+        };
+        // Pixels that are not on the edges have both horizontal neighbours
+        let inner_start = span.start.max(1);
+        let inner = inner_start..span.end.min(last_x).max(inner_start);
+        self.energies.clear();
+        self.energies.extend((span.start..inner.start).map(energy));
+        if !inner.is_empty() {
+            let vertical = top[inner.clone()].iter().zip(&bottom[inner.clone()]);
+            let left = &row[inner.start - 1..inner.end - 1];
+            let right = &row[inner.start + 1..inner.end + 1];
+            let horizontal = left.iter().zip(right);
+            self.energies.extend(
+                vertical
+                    .zip(horizontal)
+                    .map(|((t, b), (l, r))| square_diff(t, b) + square_diff(l, r)),
+            );
+        }
+        self.energies.extend((inner.end..span.end).map(energy));
+    }
+
+    /// Computes the best predecessor of each position of a range of a row,
+    /// from the costs of the previous row and from `energies`
+    fn compute_costs(&mut self, y: usize, span: Range<usize>) {
+        let previous = self.costs.row(y - 1);
+        let last_x = previous.len() - 1;
+        let (new_costs, new_dx, energies) = (&mut self.new_costs, &mut self.new_dx, &self.energies);
+        // Costs saturate like in SeamFinder, to find the same seams
+        let max_cost = max_energy(1);
+        // The leftmost of the lowest-cost predecessors
+        let best = |x: usize| {
+            let (start, end) = (x.saturating_sub(1), (x + 1).min(last_x));
+            let mut best = (previous[start], start as isize - x as isize);
+            for (p, &cost) in previous.iter().enumerate().take(end + 1).skip(start + 1) {
+                if cost < best.0 {
+                    best = (cost, p as isize - x as isize);
+                }
+            }
+            (best.0, best.1 as i8)
+        };
+        let inner_start = span.start.max(1);
+        let inner = inner_start..span.end.min(last_x).max(inner_start);
+        let offset = span.start;
+        for x in (span.start..inner.start).chain(inner.end..span.end) {
+            let (cost, dx) = best(x);
+            new_costs[x - offset] = cost.saturating_add(energies[x - offset]).min(max_cost);
+            new_dx[x - offset] = dx;
+        }
+        if !inner.is_empty() {
+            let range = inner.start - offset..inner.end - offset;
+            let left = &previous[inner.start - 1..inner.end - 1];
+            let middle = &previous[inner.clone()];
+            let right = &previous[inner.start + 1..inner.end + 1];
+            let predecessors = left.iter().zip(middle).zip(right);
+            let outputs = new_costs[range.clone()]
+                .iter_mut()
+                .zip(&mut new_dx[range.clone()]);
+            for ((((&l, &m), &r), &energy), (new_cost, new_dx)) in
+                predecessors.zip(&energies[range]).zip(outputs)
+            {
+                let (mut cost, mut dx) = (l, -1);
+                if m < cost {
+                    cost = m;
+                    dx = 0;
+                }
+                if r < cost {
+                    cost = r;
+                    dx = 1;
+                }
+                *new_cost = cost.saturating_add(energy).min(max_cost);
+                *new_dx = dx;
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn square_diff<const N: usize>(a: &[u8; N], b: &[u8; N]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(&a, &b)| {
+            let diff = i32::from(a) - i32::from(b);
+            (diff * diff) as u32
+        })
+        .sum()
+}
@@ -1,8 +1,12 @@
+#[cfg(feature = "image")]
 use crate::max_pos;
+#[cfg(feature = "image")]
 use crate::pos::Pos;
+#[cfg(feature = "image")]
 use image::{GenericImageView, Pixel};
 use num_traits::ToPrimitive;
 
+#[cfg(feature = "image")]
 pub(crate) fn energy_fn<IMG: GenericImageView>(img: &IMG, pos: Pos) -> u32 {
     let last_pos = max_pos(img);
     let [top, bottom, left, right] = pos.surrounding(last_pos);
@@ -14,7 +18,8 @@ pub(crate) fn energy_fn<IMG: GenericImageView>(img: &IMG, pos: Pos) -> u32 {
         square_diff_px(left_px, right_px)
 }
 
-fn square_diff_px<P: Pixel>(p1: P, p2: P) -> u32 {
+#[cfg(feature = "image")]
+pub(crate) fn square_diff_px<P: Pixel>(p1: P, p2: P) -> u32 {
     let (ch1, ch2) = (p1.channels(), p2.channels());
     let count = <P as Pixel>::CHANNEL_COUNT as usize;
     let mut sum = 0;
@@ -25,9 +30,9 @@ fn square_diff_px<P: Pixel>(p1: P, p2: P) -> u32 {
 }
 
 #[inline]
-fn square_diff<T: ToPrimitive>(a: T, b: T) -> u32 {
-    let a = a.to_i32().unwrap_or(i32::max_value());
-    let b = b.to_i32().unwrap_or(i32::max_value());
+pub(crate) fn square_diff<T: ToPrimitive>(a: T, b: T) -> u32 {
+    let a = a.to_i32().unwrap_or(i32::MAX);
+    let b = b.to_i32().unwrap_or(i32::MAX);
     let diff = a - b;
     (diff * diff) as u32
 }
@@ -0,0 +1,86 @@
+use image::{GenericImageView, ImageBuffer, Pixel, Primitive};
+use num_traits::NumCast;
+
+use crate::pos::Pos;
+use crate::solver::{AnySolver, ImageEnergy, SeamSolver};
+use crate::{image_view_to_buffer, max_pos, BufferOf, Carved, Options, Rotated};
+
+/// Enlarges an image to the given dimensions by inserting seams
+pub(crate) fn enlarge<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> BufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let widened = widen(img, width, options);
+    let rotated = Rotated(&widened);
+    let heightened = widen(&rotated, height, options);
+    image_view_to_buffer(&Rotated(&heightened))
+}
+
+/// Inserts vertical seams until the image has the given width,
+/// adding at most `options.max_stretch * current_width` seams at each step.
+fn widen<IMG: GenericImageView>(img: &IMG, width: u32, options: &Options) -> BufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut current = image_view_to_buffer(img);
+    while current.width() < width && current.width() > 0 {
+        let max_count = (current.width() as f32 * options.max_stretch) as u32;
+        let step = (width - current.width()).min(max_count.max(1));
+        current = insert_seams(&current, step, options);
+    }
+    current
+}
+
+/// Duplicates the `count` seams that would be removed first from the image.
+///
+/// The seams are found by carving them out one after the other, so they are
+/// disjoint in the original image. Each inserted pixel is the average of the
+/// seam pixel and its right neighbour.
+fn insert_seams<IMG: GenericImageView>(img: &IMG, count: u32, options: &Options) -> BufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(width, height) = max_pos(img);
+    let mut duplicated = vec![vec![false; width as usize]; height as usize];
+    let mut carved = Carved::new(img);
+    let mut solver = AnySolver::new(max_pos(img), options);
+    for _ in 0..count {
+        let seam = solver.extract_seam(&mut ImageEnergy(&carved));
+        for &pos in &seam {
+            let Pos(x, y) = carved.transform_pos(pos);
+            duplicated[y as usize][x as usize] = true;
+        }
+        carved.remove_seam(&seam);
+    }
+    let mut enlarged = ImageBuffer::new(width + count, height);
+    for (y, row) in duplicated.iter().enumerate() {
+        let y = y as u32;
+        let mut target_x = 0;
+        for (x, &is_duplicated) in row.iter().enumerate() {
+            let x = x as u32;
+            let px = img.get_pixel(x, y);
+            enlarged.put_pixel(target_x, y, px);
+            target_x += 1;
+            if is_duplicated {
+                let right = img.get_pixel((x + 1).min(width - 1), y);
+                enlarged.put_pixel(target_x, y, px.map2(&right, average));
+                target_x += 1;
+            }
+        }
+    }
+    enlarged
+}
+
+fn average<T: Primitive>(a: T, b: T) -> T {
+    let (a, b) = (a.to_f64().unwrap_or(0.), b.to_f64().unwrap_or(0.));
+    let mean = (a + b) / 2.;
+    // Integer channels are rounded instead of truncated
+    let is_integer = <T as NumCast>::from(0.5).map_or(true, |half: T| half == T::zero());
+    let mean = if is_integer { mean.round() } else { mean };
+    NumCast::from(mean).unwrap_or_else(T::zero)
+}
@@ -0,0 +1,331 @@
+//! Metrics comparing an image with a retargeted version of it,
+//! to evaluate energy functions and seam strategies on objective numbers.
+//!
+//! ```
+//! use seamcarving::{eval::evaluate, resize_with_plan, Options};
+//! let img = image::RgbImage::from_fn(40, 30, |x, y| image::Rgb([(x * y) as u8, x as u8, y as u8]));
+//! let (_, plan) = resize_with_plan(&img, 30, 30, &Options::default());
+//! let metrics = evaluate(&img, &plan, None).unwrap();
+//! assert!(metrics.bidirectional_similarity > 0.);
+//! ```
+use image::imageops::{resize, FilterType};
+use image::{GenericImageView, GrayImage, Pixel};
+use num_traits::ToPrimitive;
+
+use crate::carved2d::Carved2D;
+use crate::energy::{energy_fn, square_diff_px};
+use crate::plan::{Axis, CarvingPlan, PlanError};
+use crate::pos::Pos;
+use crate::replay;
+
+/// Largest width and height of the copies of the images on which
+/// [bidirectional_similarity] is computed by [evaluate]
+pub const BDS_SIZE: u32 = 96;
+/// Width and height of the patches compared by [evaluate]
+pub const BDS_PATCH_SIZE: u32 = 5;
+
+/// Metrics of a retargeting, as computed by [evaluate]. Lower is better for all of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metrics {
+    /// The [bidirectional_similarity] between the original and the retargeted image
+    pub bidirectional_similarity: f64,
+    /// The sum of the energies of the removed pixels, in the original image
+    pub removed_energy: u64,
+    /// The mean squared difference between the pixels that became neighbours when a pixel
+    /// was removed, summed over the channels. High values denote visible discontinuities.
+    pub edge_distortion: f64,
+    /// The fraction of the pixels of the protection mask that were removed,
+    /// when a mask is given
+    pub protected_lost: Option<f64>,
+}
+
+/// Computes the [Metrics] of the removal of the seams of a plan from an image.
+///
+/// Pixels of `mask` that are not zero are protected.
+/// Fails if the image or the mask do not have the size of the plan, or if the plan is invalid.
+pub fn evaluate<IMG: GenericImageView>(
+    original: &IMG,
+    plan: &CarvingPlan,
+    mask: Option<&GrayImage>,
+) -> Result<Metrics, PlanError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    if mask.map_or(false, |mask| mask.dimensions() != (plan.width, plan.height)) {
+        return Err(PlanError::SizeMismatch);
+    }
+    let retargeted = replay(original, plan)?;
+    let (width, height) = original.dimensions();
+    let scale = f64::from(BDS_SIZE) / f64::from(width.max(height).max(BDS_SIZE));
+    let scaled = |(w, h): (u32, u32)| {
+        let scale = |d: u32| ((f64::from(d) * scale).round() as u32).max(1);
+        (scale(w), scale(h))
+    };
+    let (w, h) = scaled(original.dimensions());
+    let small_original = resize(original, w, h, FilterType::Triangle);
+    let (w, h) = scaled(retargeted.dimensions());
+    let small_retargeted = resize(&retargeted, w, h, FilterType::Triangle);
+
+    let mut removed_energy = 0;
+    let (mut distortion, mut neighbours) = (0, 0u64);
+    let mut protected_lost = 0;
+    for_each_removal(original, plan, |removed, new_neighbours| {
+        removed_energy += u64::from(energy_fn(original, removed));
+        if let Some((a, b)) = new_neighbours {
+            let (a, b) = (original.get_pixel(a.0, a.1), original.get_pixel(b.0, b.1));
+            distortion += u64::from(square_diff_px(a, b));
+            neighbours += 1;
+        }
+        if mask.map_or(false, |mask| mask.get_pixel(removed.0, removed.1)[0] != 0) {
+            protected_lost += 1;
+        }
+    });
+    let protected_lost = mask.map(|mask| {
+        let protected = mask.pixels().filter(|p| p[0] != 0).count();
+        if protected == 0 {
+            0.
+        } else {
+            protected_lost as f64 / protected as f64
+        }
+    });
+    Ok(Metrics {
+        bidirectional_similarity: bidirectional_similarity(
+            &small_original,
+            &small_retargeted,
+            BDS_PATCH_SIZE,
+        ),
+        removed_energy,
+        edge_distortion: if neighbours == 0 {
+            0.
+        } else {
+            distortion as f64 / neighbours as f64
+        },
+        protected_lost,
+    })
+}
+
+/// The bidirectional similarity distance of Simakov et al.:
+/// the mean distance of each patch of `source` to the most similar patch of `target`
+/// (completeness), plus the mean distance of each patch of `target` to the most similar
+/// patch of `source` (coherence).
+///
+/// Patches are squares of `patch_size` pixels, and their distance is the mean squared difference
+/// of their channels. Similar patches are searched in a window around the position
+/// that corresponds to the patch in the other image, as large as the difference
+/// between the sizes of the images. The cost is proportional to the number of pixels
+/// times the size of that window, so large images should be downscaled first, as
+/// [evaluate] does.
+pub fn bidirectional_similarity<A, B>(source: &A, target: &B, patch_size: u32) -> f64
+where
+    A: GenericImageView,
+    B: GenericImageView<Pixel = A::Pixel>,
+{
+    let (source, target) = (Channels::new(source), Channels::new(target));
+    let patch_size = patch_size
+        .min(source.width)
+        .min(source.height)
+        .min(target.width)
+        .min(target.height);
+    if patch_size == 0 {
+        return 0.;
+    }
+    completeness(&source, &target, patch_size) + completeness(&target, &source, patch_size)
+}
+
+/// The mean distance of each patch of `a` to the most similar patch of `b`
+fn completeness(a: &Channels, b: &Channels, patch_size: u32) -> f64 {
+    let radius_x = a.width.abs_diff(b.width) + 1;
+    let radius_y = a.height.abs_diff(b.height) + 1;
+    let (a_columns, a_rows) = (a.width - patch_size + 1, a.height - patch_size + 1);
+    let (b_columns, b_rows) = (b.width - patch_size + 1, b.height - patch_size + 1);
+    let mut total = 0.;
+    for y in 0..a_rows {
+        let center_y = y * b_rows / a_rows;
+        let rows = center_y.saturating_sub(radius_y)..(center_y + radius_y + 1).min(b_rows);
+        for x in 0..a_columns {
+            let center_x = x * b_columns / a_columns;
+            let columns =
+                center_x.saturating_sub(radius_x)..(center_x + radius_x + 1).min(b_columns);
+            let mut best = f64::INFINITY;
+            for v in rows.clone() {
+                for u in columns.clone() {
+                    best = best.min(patch_distance(a, Pos(x, y), b, Pos(u, v), patch_size, best));
+                }
+            }
+            total += best;
+        }
+    }
+    total / f64::from(a_columns * a_rows)
+}
+
+/// The mean squared difference between two patches,
+/// or a value at least `bound` as soon as it is known to exceed it
+fn patch_distance(
+    a: &Channels,
+    pa: Pos,
+    b: &Channels,
+    pb: Pos,
+    patch_size: u32,
+    bound: f64,
+) -> f64 {
+    let row_len = patch_size as usize * a.channels;
+    let count = (row_len * patch_size as usize) as f64;
+    let limit = bound * count;
+    let mut sum = 0.;
+    for dy in 0..patch_size {
+        let row_a = a.row(Pos(pa.0, pa.1 + dy), row_len);
+        let row_b = b.row(Pos(pb.0, pb.1 + dy), row_len);
+        sum += row_a
+            .iter()
+            .zip(row_b)
+            .map(|(&x, &y)| (x - y) * (x - y))
+            .sum::<f64>();
+        if sum > limit {
+            break;
+        }
+    }
+    sum / count
+}
+
+/// The channels of the pixels of an image, row by row
+struct Channels {
+    width: u32,
+    height: u32,
+    channels: usize,
+    values: Vec<f64>,
+}
+
+impl Channels {
+    fn new<IMG: GenericImageView>(img: &IMG) -> Self {
+        let (width, height) = img.dimensions();
+        let channels = <IMG::Pixel as Pixel>::CHANNEL_COUNT as usize;
+        let mut values = Vec::with_capacity(width as usize * height as usize * channels);
+        for (_, _, pixel) in img.pixels() {
+            values.extend(pixel.channels().iter().map(|c| c.to_f64().unwrap_or(0.)));
+        }
+        Channels {
+            width,
+            height,
+            channels,
+            values,
+        }
+    }
+
+    fn row(&self, Pos(x, y): Pos, len: usize) -> &[f64] {
+        let start = (y as usize * self.width as usize + x as usize) * self.channels;
+        &self.values[start..start + len]
+    }
+}
+
+/// Calls `f` for each pixel removed by a plan, with its position in the original image,
+/// and the positions of the two pixels on each side of it, that become neighbours,
+/// if it is not on the border of the image
+fn for_each_removal<IMG: GenericImageView>(
+    original: &IMG,
+    plan: &CarvingPlan,
+    mut f: impl FnMut(Pos, Option<(Pos, Pos)>),
+) {
+    let mut view = Carved2D::new(original);
+    for seam in &plan.seams {
+        let (width, height) = view.dimensions();
+        for pos in seam.positions() {
+            let (pos, before, after) = match seam.axis {
+                Axis::Vertical => (
+                    pos,
+                    pos.0.checked_sub(1).map(|x| Pos(x, pos.1)),
+                    Pos(pos.0 + 1, pos.1),
+                ),
+                Axis::Horizontal => {
+                    let pos = Pos(pos.1, pos.0);
+                    (
+                        pos,
+                        pos.1.checked_sub(1).map(|y| Pos(pos.0, y)),
+                        Pos(pos.0, pos.1 + 1),
+                    )
+                }
+            };
+            let neighbours = match before {
+                Some(before) if after.0 < width && after.1 < height => {
+                    Some((view.transform_pos(before), view.transform_pos(after)))
+                }
+                _ => None,
+            };
+            f(view.transform_pos(pos), neighbours);
+        }
+        view.remove(seam);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+    use crate::plan::Seam;
+
+    #[test]
+    fn metrics_of_an_empty_plan() {
+        let img = GrayImage::from_fn(12, 10, |x, y| Luma([(x * 31 + y * 17) as u8]));
+        let plan = CarvingPlan {
+            width: 12,
+            height: 10,
+            seams: vec![],
+        };
+        let mask = GrayImage::from_pixel(12, 10, Luma([1]));
+        let metrics = evaluate(&img, &plan, Some(&mask)).unwrap();
+        assert_eq!(
+            metrics,
+            Metrics {
+                bidirectional_similarity: 0.,
+                removed_energy: 0,
+                edge_distortion: 0.,
+                protected_lost: Some(0.),
+            }
+        );
+    }
+
+    #[test]
+    fn metrics_of_removed_seams() {
+        // Columns of 0 and 10, except for column 2, which has the value of its left neighbour
+        let img = GrayImage::from_fn(6, 4, |x, _| {
+            Luma([if x == 2 || x % 2 == 1 { 10 } else { 0 }])
+        });
+        let vertical = Seam {
+            axis: Axis::Vertical,
+            offsets: vec![2; 4],
+        };
+        let horizontal = Seam {
+            axis: Axis::Horizontal,
+            offsets: vec![0; 5],
+        };
+        let plan = CarvingPlan {
+            width: 6,
+            height: 4,
+            seams: vec![vertical, horizontal],
+        };
+        let mask = GrayImage::from_fn(6, 4, |x, y| Luma([(x == 0 && y < 2) as u8]));
+        let metrics = evaluate(&img, &plan, Some(&mask)).unwrap();
+        // Column 2 is between two pixels of 10, so has no energy. The first row is then
+        // removed, whose pixels are between a 0 and a 10, except for column 4.
+        assert_eq!(metrics.removed_energy, 4 * 100);
+        // 10 and 10 become neighbours, and there is no neighbour above the first row
+        assert_eq!(metrics.edge_distortion, 0.);
+        assert_eq!(metrics.protected_lost, Some(0.5));
+        assert!(metrics.bidirectional_similarity > 0.);
+
+        let wrong_mask = GrayImage::new(5, 4);
+        assert_eq!(
+            evaluate(&img, &plan, Some(&wrong_mask)),
+            Err(PlanError::SizeMismatch)
+        );
+    }
+
+    #[test]
+    fn similarity_is_symmetric_for_equal_sizes() {
+        let a = GrayImage::from_fn(9, 7, |x, y| Luma([(x * y * 7) as u8]));
+        let b = GrayImage::from_fn(9, 7, |x, y| Luma([(x * y * 7 + 3) as u8]));
+        let d = bidirectional_similarity(&a, &b, 3);
+        assert_eq!(d, bidirectional_similarity(&b, &a, 3));
+        assert!(d > 0. && d <= 2. * 9.);
+    }
+}
@@ -0,0 +1,307 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::pos::Pos;
+use crate::solver::{EnergyMap, SeamSolver};
+use crate::Options;
+
+/// Finds seams by computing a minimum cut in a graph whose nodes are the pixels,
+/// as described by Rubinstein, Shamir and Avidan in
+/// *Improved Seam Carving for Video Retargeting* (2008).
+///
+/// Contrary to the dynamic programming approach of [SeamFinder](crate::seam_finder::SeamFinder),
+/// this works on a stack of frames, where it finds a seam surface.
+/// It is also much slower, and keeps no state between two seams.
+///
+/// Among seams of equal cost, the leftmost one is returned.
+#[derive(Debug)]
+pub(crate) struct GraphCut {
+    size: Pos,
+    max_step: u32,
+    forward_energy: bool,
+}
+
+impl GraphCut {
+    pub fn new(size: Pos, options: &Options, forward_energy: bool) -> Self {
+        GraphCut {
+            size,
+            max_step: u32::from(options.max_step),
+            forward_energy,
+        }
+    }
+
+    /// Finds a seam surface in a stack of frames that all have the size of the solver.
+    ///
+    /// Returns one seam per frame. The seams of two consecutive frames
+    /// are at most one pixel apart on each row.
+    pub fn extract_surface<E: EnergyMap>(&mut self, frames: &mut [E]) -> Vec<Vec<Pos>> {
+        let Pos(width, height) = self.size;
+        if width == 0 || height == 0 {
+            return vec![vec![]; frames.len()];
+        }
+        let mut graph = self.build_graph(frames);
+        let (source, sink) = (graph.len() - 2, graph.len() - 1);
+        graph.max_flow(source, sink);
+        let reachable = graph.reachable_from(source);
+        // On each row, the seam is the last pixel on the side of the source
+        let surface = (0..frames.len())
+            .map(|t| {
+                (0..height)
+                    .rev()
+                    .map(|y| {
+                        let row_start = self.node(0, y, t);
+                        let x = (0..width)
+                            .rev()
+                            .find(|&x| reachable[row_start + x as usize])
+                            .unwrap_or(0);
+                        Pos(x, y)
+                    })
+                    .collect()
+            })
+            .collect();
+        self.size.0 -= 1;
+        surface
+    }
+
+    #[inline(always)]
+    fn node(&self, x: u32, y: u32, t: usize) -> usize {
+        let Pos(width, height) = self.size;
+        (t * height as usize + y as usize) * width as usize + x as usize
+    }
+
+    fn build_graph<E: EnergyMap>(&self, frames: &mut [E]) -> FlowGraph {
+        let Pos(width, height) = self.size;
+        let node_count = width as usize * height as usize * frames.len();
+        let (source, sink) = (node_count, node_count + 1);
+        let mut graph = FlowGraph::new(node_count + 2);
+        let step = self.max_step;
+        for (t, energy) in frames.iter_mut().enumerate() {
+            for y in 0..height {
+                graph.add_edge(source, self.node(0, y, t), INFINITY, 0);
+                for x in 0..width {
+                    let node = self.node(x, y, t);
+                    // Cutting this edge means that (x, y) is the pixel of the seam on its row.
+                    // The infinite reverse edge ensures that each row is cut exactly once.
+                    let cost = if self.forward_energy {
+                        let left = Pos(x.saturating_sub(1), y);
+                        let right = Pos((x + 1).min(width - 1), y);
+                        energy.distance(left, right)
+                    } else {
+                        energy.energy(Pos(x, y))
+                    };
+                    let next = if x + 1 < width { self.node(x + 1, y, t) } else { sink };
+                    graph.add_edge(node, next, u64::from(cost), INFINITY);
+                    // Infinite diagonal edges ensure the seam is connected
+                    if x >= step {
+                        if y > 0 {
+                            graph.add_edge(node, self.node(x - step, y - 1, t), INFINITY, 0);
+                        }
+                        if y + 1 < height {
+                            graph.add_edge(node, self.node(x - step, y + 1, t), INFINITY, 0);
+                        }
+                    }
+                    if x >= 1 && t > 0 {
+                        graph.add_edge(node, self.node(x - 1, y, t - 1), INFINITY, 0);
+                        graph.add_edge(self.node(x, y, t - 1), self.node(x - 1, y, t), INFINITY, 0);
+                    }
+                    if self.forward_energy && x >= 1 && y >= 1 {
+                        // Cost of the new edges created between rows when the seam moves
+                        let up = self.node(x, y - 1, t);
+                        let to_left = energy.distance(Pos(x, y - 1), Pos(x - 1, y));
+                        let to_right = energy.distance(Pos(x - 1, y - 1), Pos(x, y));
+                        graph.add_edge(node, up, u64::from(to_left), u64::from(to_right));
+                    }
+                }
+            }
+        }
+        graph
+    }
+}
+
+impl SeamSolver for GraphCut {
+    fn extract_seam<E: EnergyMap>(&mut self, energy: &mut E) -> Vec<Pos> {
+        self.extract_surface(core::slice::from_mut(energy))
+            .pop()
+            .unwrap_or_default()
+    }
+
+    fn restore_seam(&mut self, _seam: &[Pos]) {
+        self.size.0 += 1;
+    }
+
+    fn remove_seam(&mut self, _seam: &[Pos]) {
+        self.size.0 -= 1;
+    }
+}
+
+const INFINITY: u64 = u64::MAX / 4;
+const NONE: u32 = u32::MAX;
+
+/// A directed graph with edge capacities, on which the maximum flow can be computed
+/// using Dinic's algorithm
+struct FlowGraph {
+    // For each node, the index of its first outgoing edge
+    first_edge: Vec<u32>,
+    // For each edge, the index of the next edge that has the same origin
+    next_edge: Vec<u32>,
+    // For each edge, its destination node
+    target: Vec<u32>,
+    // For each edge, the remaining capacity.
+    // Edges are stored in pairs: the reverse of edge e is e ^ 1
+    capacity: Vec<u64>,
+    // Distance from the source, used during the computation of the flow
+    level: Vec<u32>,
+}
+
+impl FlowGraph {
+    fn new(node_count: usize) -> Self {
+        FlowGraph {
+            first_edge: vec![NONE; node_count],
+            next_edge: vec![],
+            target: vec![],
+            capacity: vec![],
+            level: vec![NONE; node_count],
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.first_edge.len()
+    }
+
+    fn add_edge(&mut self, a: usize, b: usize, capacity: u64, reverse_capacity: u64) {
+        for &(from, to, c) in &[(a, b, capacity), (b, a, reverse_capacity)] {
+            self.next_edge.push(self.first_edge[from]);
+            self.first_edge[from] = self.target.len() as u32;
+            self.target.push(to as u32);
+            self.capacity.push(c);
+        }
+    }
+
+    fn max_flow(&mut self, source: usize, sink: usize) -> u64 {
+        let mut total = 0;
+        while self.compute_levels(source, sink) {
+            let mut current_edge = self.first_edge.clone();
+            let mut path: Vec<u32> = vec![];
+            let mut node = source;
+            loop {
+                if node == sink {
+                    let flow = path.iter().map(|&e| self.capacity[e as usize]).min().unwrap_or(0);
+                    for &e in &path {
+                        self.capacity[e as usize] -= flow;
+                        self.capacity[(e ^ 1) as usize] += flow;
+                    }
+                    total += flow;
+                    path.clear();
+                    node = source;
+                    continue;
+                }
+                // Advance along an edge that is not saturated and goes one level deeper
+                let mut e = current_edge[node];
+                while e != NONE {
+                    let next = self.target[e as usize] as usize;
+                    if self.capacity[e as usize] > 0 && self.level[next] == self.level[node] + 1 {
+                        break;
+                    }
+                    e = self.next_edge[e as usize];
+                }
+                current_edge[node] = e;
+                if e != NONE {
+                    path.push(e);
+                    node = self.target[e as usize] as usize;
+                } else {
+                    // Dead end: retreat
+                    self.level[node] = NONE;
+                    match path.pop() {
+                        Some(e) => {
+                            node = self.target[(e ^ 1) as usize] as usize;
+                            current_edge[node] = self.next_edge[e as usize];
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+        total
+    }
+
+    /// Computes the distance of each node to the source in the residual graph.
+    /// Returns whether the sink is reachable.
+    fn compute_levels(&mut self, source: usize, sink: usize) -> bool {
+        self.level.iter_mut().for_each(|l| *l = NONE);
+        self.level[source] = 0;
+        let mut queue = VecDeque::new();
+        queue.push_back(source);
+        while let Some(node) = queue.pop_front() {
+            let mut e = self.first_edge[node];
+            while e != NONE {
+                let next = self.target[e as usize] as usize;
+                if self.capacity[e as usize] > 0 && self.level[next] == NONE {
+                    self.level[next] = self.level[node] + 1;
+                    queue.push_back(next);
+                }
+                e = self.next_edge[e as usize];
+            }
+        }
+        self.level[sink] != NONE
+    }
+
+    /// The nodes that can be reached from the source in the residual graph
+    fn reachable_from(&mut self, source: usize) -> Vec<bool> {
+        self.compute_levels(source, source);
+        self.level.iter().map(|&l| l != NONE).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph_cut::GraphCut;
+    use crate::pos::Pos;
+    use crate::seam_finder::SeamFinder;
+    use crate::solver::EnergyMap;
+    use crate::Options;
+
+    struct Pseudorandom(u32);
+
+    impl EnergyMap for Pseudorandom {
+        fn energy(&mut self, Pos(x, y): Pos) -> u32 {
+            (x * 7919 + y * 104_729 + self.0).wrapping_mul(2_654_435_761) % 100
+        }
+        fn distance(&mut self, a: Pos, b: Pos) -> u32 {
+            self.energy(a).abs_diff(self.energy(b))
+        }
+    }
+
+    fn seam_cost(seam: &[Pos], energy: &mut Pseudorandom) -> u32 {
+        seam.iter().map(|&p| energy.energy(p)).sum()
+    }
+
+    #[test]
+    fn same_cost_as_dynamic_programming() {
+        let options = Options::default();
+        for seed in 0..10 {
+            let size = Pos(12, 9);
+            let mut energy = Pseudorandom(seed);
+            let dp_seam = SeamFinder::new(size, &options).extract_seam(|p| energy.energy(p));
+            let cut_seam = GraphCut::new(size, &options, false)
+                .extract_surface(core::slice::from_mut(&mut energy))
+                .remove(0);
+            assert_eq!(seam_cost(&cut_seam, &mut energy), seam_cost(&dp_seam, &mut energy));
+            assert!(cut_seam.windows(2).all(|w| w[0].0.abs_diff(w[1].0) <= 1));
+        }
+    }
+
+    #[test]
+    fn surfaces_are_connected_in_time() {
+        let options = Options::default();
+        let mut frames: Vec<_> = (0..4).map(|t| Pseudorandom(t * 1000)).collect();
+        let surface = GraphCut::new(Pos(10, 6), &options, true).extract_surface(&mut frames);
+        assert_eq!(surface.len(), 4);
+        for seams in surface.windows(2) {
+            for (a, b) in seams[0].iter().zip(&seams[1]) {
+                assert_eq!(a.1, b.1);
+                assert!(a.0.abs_diff(b.0) <= 1);
+            }
+        }
+    }
+}
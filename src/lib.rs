@@ -4,21 +4,91 @@
 //! it takes an image, and removes horizontal and vertical seams
 //! until it fits a given size.
 //!
+//! It works on the images of the [image] crate, with the default `image` feature.
+//! Without it, the [raw] module carves images stored in plain slices.
+//! Without the `std` feature, which `image` enables, the crate is `no_std`
+//! and only needs [alloc].
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// Parts of the seam finders are only used by the image layer
+#![cfg_attr(not(feature = "image"), allow(dead_code))]
+
+extern crate alloc;
+
+#[cfg(feature = "image")]
+use std::ops::Deref;
+
+#[cfg(feature = "image")]
 use image::{GenericImageView, ImageBuffer, Pixel};
 
+#[cfg(feature = "image")]
+pub use crate::animation::{
+    read_apng, read_gif, resize_animation, write_apng, write_gif, AnimationFrame, Disposal,
+};
+#[cfg(feature = "image")]
+pub use crate::aspect::resize_to_aspect;
+#[cfg(feature = "image")]
 pub use crate::carved::Carved;
-use crate::energy::energy_fn;
+#[cfg(feature = "image")]
+use crate::carved::PendingCarved;
+#[cfg(feature = "image")]
+pub use crate::carved2d::Carved2D;
+#[cfg(feature = "image")]
+use crate::carved2d::PendingCarved2D;
+#[cfg(feature = "image")]
+use crate::enlarge::enlarge;
+#[cfg(feature = "image")]
+use crate::options::allows_seam;
+pub use crate::options::{Options, Solver, StopCriterion, TieBreak};
+pub use crate::plan::{Axis, CarvingPlan, PlanError, Seam};
 use crate::pos::Pos;
+#[cfg(feature = "image")]
 pub use crate::rotated::Rotated;
-use crate::seam_finder::SeamFinder;
+#[cfg(feature = "image")]
+use crate::solver::{seam_cost, AnySolver, ImageEnergy, SeamSolver};
+#[cfg(feature = "image")]
+pub use crate::stream::{carve_rows, CarvedRows};
+#[cfg(feature = "image")]
+pub use crate::video::{resize_video, resize_video_surfaces};
 
+mod aliases;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "image")]
+mod animation;
+#[cfg(feature = "image")]
+mod aspect;
+#[cfg(feature = "image")]
 mod carved;
+#[cfg(feature = "image")]
+mod carved2d;
+#[cfg(feature = "image")]
+mod dense;
 mod energy;
+#[cfg(feature = "image")]
+mod enlarge;
+#[cfg(feature = "image")]
+pub mod eval;
+mod graph_cut;
 mod matrix;
+mod options;
+pub mod plan;
 mod pos;
+#[cfg(feature = "image")]
+mod pyramid;
+pub mod raw;
+#[cfg(feature = "image")]
 mod rotated;
 mod seam_finder;
+mod solver;
+#[cfg(feature = "image")]
+mod stream;
+#[cfg(feature = "image")]
+mod video;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+#[cfg(feature = "image")]
 /// Resizes an image to a lower width and height,
 /// using seam carving to avoid deforming the contents.
 ///
@@ -28,6 +98,7 @@ mod seam_finder;
 ///
 /// If the image is already smaller than the given dimensions,
 /// then the returned image is identical to the input.
+/// Use [resize_with_options] to enlarge images.
 ///
 /// ```no_run
 /// let img = image::open("./my_image.jpg").unwrap();
@@ -39,21 +110,308 @@ pub fn resize<IMG: GenericImageView>(
     width: u32,
     height: u32,
 ) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(width, height) = Pos(width.min(img.width()), height.min(img.height()));
+    resize_with_options(img, width, height, &Options::default())
+}
+
+#[cfg(feature = "image")]
+/// Resizes an image to the given width and height,
+/// using seam carving to avoid deforming the contents.
+///
+/// Dimensions that are smaller than the ones of the image are reduced by removing seams.
+/// Dimensions that are larger are increased by duplicating the seams that would
+/// have been removed first, in several steps controlled by [Options::max_stretch].
+///
+/// ```no_run
+/// use image::GenericImageView;
+/// let img = image::open("./my_image.jpg").unwrap();
+/// let options = seamcarving::Options::default().max_stretch(0.3);
+/// let (width, height) = img.dimensions();
+/// let resized = seamcarving::resize_with_options(&img, width * 2, height, &options);
+/// ```
+pub fn resize_with_options<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> BufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(max_width, max_height) = max_pos(img);
+    if width > max_width || height > max_height {
+        let enlarged = enlarge(img, width.max(max_width), height.max(max_height), options);
+        return shrink(&enlarged, width, height, options);
+    }
+    shrink(img, width, height, options)
+}
+
+#[cfg(feature = "image")]
+fn shrink<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> BufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    shrink_with_plan(img, width, height, options).0
+}
+
+#[cfg(feature = "image")]
+fn shrink_with_plan<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> (BufferOf<IMG>, CarvingPlan, Vec<u64>)
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let (view, plan, costs) = carve_view(img, width, height, options);
+    (image_view_to_buffer(&view), plan, costs)
+}
+
+#[cfg(feature = "image")]
+/// Removes vertical and then horizontal seams until the image fits in the given dimensions,
+/// or until the stop criterion of the options rejects a seam.
+/// Returns the cost of each removed seam with the plan.
+fn carve_view<'a, IMG: GenericImageView>(
+    img: &'a IMG,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> (Carved2D<'a, IMG>, CarvingPlan, Vec<u64>)
 where
     <IMG as GenericImageView>::Pixel: 'static,
 {
     let Pos(to_remove_x, to_remove_y) = max_pos(img) - Pos(width, height);
-    let carved_x = carve(img, to_remove_x);
-    let rotated = Rotated(&carved_x);
-    let carved_y = carve(&rotated, to_remove_y);
-    let re_rotated = Rotated(&carved_y);
-    image_view_to_buffer(&re_rotated)
+    let mut view = Carved2D::new(img);
+    let mut seams = Vec::with_capacity((to_remove_x + to_remove_y) as usize);
+    let mut costs = Vec::with_capacity((to_remove_x + to_remove_y) as usize);
+    let mut solver = AnySolver::new(max_pos(img), options);
+    let (per_pass, stop) = (options.seams_per_pass, options.stop);
+    let factor = options.pyramid_factor;
+    let found = if factor > 1 {
+        Some(pyramid::find_seams(img, to_remove_x, factor, options))
+    } else {
+        dense::find_seams(img, to_remove_x, options)
+    };
+    if let Some(found) = found {
+        for seam in found {
+            let cost = seam_cost(&mut ImageEnergy(&view), &seam);
+            if !allows_seam(stop, &costs, cost) {
+                break;
+            }
+            view.remove_vertical_seam(&seam);
+            seams.push(Seam::from_positions(Axis::Vertical, &seam));
+            costs.push(cost);
+        }
+    } else if per_pass > 1 {
+        let mut remaining = to_remove_x;
+        'passes: while remaining > 0 {
+            let found = solver.extract_seams(&mut ImageEnergy(&view), remaining.min(per_pass));
+            remaining -= found.len() as u32;
+            for seam in found {
+                let cost = seam_cost(&mut ImageEnergy(&view), &seam);
+                if !allows_seam(stop, &costs, cost) {
+                    break 'passes;
+                }
+                view.remove_vertical_seam(&seam);
+                seams.push(Seam::from_positions(Axis::Vertical, &seam));
+                costs.push(cost);
+            }
+        }
+    } else {
+        // Removals are deferred, and compacted when enough of them are pending
+        for _ in 0..to_remove_x {
+            let pending = PendingCarved2D(&view);
+            let energy = &mut ImageEnergy(&pending);
+            let seam = solver.extract_seam_deferred(energy);
+            let cost = seam_cost(energy, &seam);
+            if !allows_seam(stop, &costs, cost) {
+                break;
+            }
+            view.defer_vertical_seam_removal(&seam);
+            seams.push(Seam::from_positions(Axis::Vertical, &seam));
+            costs.push(cost);
+        }
+        solver.compact();
+        view.compact();
+    }
+    let vertical = costs.len();
+    let found = if factor > 1 {
+        Some(pyramid::find_seams(&Rotated(&view), to_remove_y, factor, options))
+    } else {
+        dense::find_seams(&Rotated(&view), to_remove_y, options)
+    };
+    if let Some(found) = found {
+        for seam in found {
+            let cost = seam_cost(&mut ImageEnergy(&Rotated(&view)), &seam);
+            if !allows_seam(stop, &costs[vertical..], cost) {
+                break;
+            }
+            view.remove_horizontal_seam(&seam);
+            seams.push(Seam::from_positions(Axis::Horizontal, &seam));
+            costs.push(cost);
+        }
+    } else {
+        let mut solver = AnySolver::new(max_pos(&Rotated(&view)), options);
+        let mut remaining = to_remove_y;
+        'horizontal: while remaining > 0 {
+            let rotated = Rotated(&view);
+            let found = solver.extract_seams(&mut ImageEnergy(&rotated), remaining.min(per_pass));
+            remaining -= found.len() as u32;
+            for seam in found {
+                let cost = seam_cost(&mut ImageEnergy(&Rotated(&view)), &seam);
+                if !allows_seam(stop, &costs[vertical..], cost) {
+                    break 'horizontal;
+                }
+                view.remove_horizontal_seam(&seam);
+                seams.push(Seam::from_positions(Axis::Horizontal, &seam));
+                costs.push(cost);
+            }
+        }
+    }
+    let Pos(width, height) = max_pos(img);
+    (view, CarvingPlan { width, height, seams }, costs)
 }
 
+#[cfg(feature = "image")]
+/// Resizes an image to a lower width and height like [resize],
+/// but returns a lazily carved view of the image instead of a new buffer.
+///
+/// ```
+/// use image::GenericImageView;
+/// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let view = seamcarving::resize_view(&img, 15, 8);
+/// assert_eq!(view.dimensions(), (15, 8));
+/// assert_eq!(seamcarving::image_view_to_buffer(&view), seamcarving::resize(&img, 15, 8));
+/// ```
+pub fn resize_view<IMG: GenericImageView>(img: &IMG, width: u32, height: u32) -> Carved2D<'_, IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(width, height) = Pos(width.min(img.width()), height.min(img.height()));
+    carve_view(img, width, height, &Options::default()).0
+}
+
+#[cfg(feature = "image")]
+/// Resizes an image buffer to a lower width and height like [resize],
+/// and writes the result to an existing buffer, reusing its allocation.
+///
+/// ```
+/// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let mut out = image::GrayImage::new(0, 0);
+/// for width in 12..16 {
+///     seamcarving::resize_into(&img, width, 8, &mut out);
+///     assert_eq!(out, seamcarving::resize(&img, width, 8));
+/// }
+/// ```
+pub fn resize_into<P, C>(
+    img: &ImageBuffer<P, C>,
+    width: u32,
+    height: u32,
+    out: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+) where
+    P: Pixel + 'static,
+    C: Deref<Target = [P::Subpixel]>,
+{
+    resize_view(img, width, height).copy_into(out)
+}
+
+#[cfg(feature = "image")]
+/// Resizes an image to a lower width and height like [resize_with_options],
+/// and returns the seams that were removed.
+///
+/// Dimensions larger than the ones of the image are left unchanged.
+/// The plan can be saved and later applied with [replay]
+/// to another image of the same size, without recomputing energies.
+///
+/// ```
+/// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let (resized, plan) = seamcarving::resize_with_plan(&img, 15, 8, &Default::default());
+/// assert_eq!(seamcarving::replay(&img, &plan).unwrap(), resized);
+/// ```
+pub fn resize_with_plan<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> (BufferOf<IMG>, CarvingPlan)
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let (resized, plan, _) = resize_with_costs(img, width, height, options);
+    (resized, plan)
+}
+
+#[cfg(feature = "image")]
+/// Resizes an image to a lower width and height like [resize_with_plan],
+/// and also returns the cost of each removed seam, in the order of the plan.
+///
+/// The cost of a seam is the sum of the current energies of its pixels when it is removed.
+/// With a [stop criterion](Options::stop_when), the image can be left larger than requested.
+///
+/// ```
+/// use image::GenericImageView;
+/// use seamcarving::{Options, StopCriterion};
+/// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let options = Options::default().stop_when(StopCriterion::MaxCost(1000));
+/// let (resized, plan, costs) = seamcarving::resize_with_costs(&img, 10, 10, &options);
+/// assert_eq!(costs.len(), plan.seams.len());
+/// assert!(costs.iter().all(|&cost| cost <= 1000));
+/// assert_eq!(resized.width(), 20 - costs.len() as u32);
+/// ```
+pub fn resize_with_costs<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> (BufferOf<IMG>, CarvingPlan, Vec<u64>)
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(width, height) = Pos(width.min(img.width()), height.min(img.height()));
+    shrink_with_plan(img, width, height, options)
+}
+
+#[cfg(feature = "image")]
+/// Removes the seams of a [CarvingPlan] from an image.
+///
+/// The image must have the same dimensions as the one the plan was made for.
+/// Seams are removed in the order of the plan, without computing any energy.
+pub fn replay<IMG: GenericImageView>(
+    img: &IMG,
+    plan: &CarvingPlan,
+) -> Result<BufferOf<IMG>, PlanError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    if max_pos(img) != Pos(plan.width, plan.height) {
+        return Err(PlanError::SizeMismatch);
+    }
+    plan.validate()?;
+    let mut view = Carved2D::new(img);
+    plan.seams.iter().for_each(|seam| view.remove(seam));
+    Ok(image_view_to_buffer(&view))
+}
+
+#[cfg(feature = "image")]
+/// An owned image buffer with the same pixel type as `IMG`
+pub(crate) type BufferOf<IMG> =
+    ImageBuffer<<IMG as GenericImageView>::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>;
+
+#[cfg(feature = "image")]
 fn max_pos<IMG: GenericImageView>(img: &IMG) -> Pos {
     Pos(img.width(), img.height())
 }
 
+#[cfg(feature = "image")]
 /// A structure that allows removing vertical seams of content
 /// from an image
 pub struct Carvable<'a, IMG: GenericImageView>
@@ -61,34 +419,181 @@ where
     <IMG as GenericImageView>::Pixel: 'a,
 {
     carved: Carved<'a, IMG>,
-    seam_finder: SeamFinder,
+    solver: AnySolver,
+    seams: Vec<Seam>,
+    costs: Vec<u64>,
+    // Seams put back by restore_seam, with their costs, removed again first by set_width
+    restored: Vec<(Seam, u64)>,
+    seams_per_pass: u32,
+    stop: Option<StopCriterion>,
 }
 
+#[cfg(feature = "image")]
 impl<'a, IMG: GenericImageView> Carvable<'a, IMG> {
     /// Creates a new proxy object that will allow reducing an image width.
     /// Notice that it does not take a mutable pointer.
     /// The underlying image itself is untouched.
     pub fn new(img: &'a IMG) -> Self {
+        Self::with_options(img, &Options::default())
+    }
+    /// Creates a new proxy object that will remove seams according to the given options.
+    pub fn with_options(img: &'a IMG, options: &Options) -> Self {
         let carved = Carved::new(img);
-        let seam_finder = SeamFinder::new(max_pos(img));
+        let solver = AnySolver::new(max_pos(img), options);
         Carvable {
             carved,
-            seam_finder,
+            solver,
+            seams: vec![],
+            costs: vec![],
+            restored: vec![],
+            seams_per_pass: options.seams_per_pass,
+            stop: options.stop,
         }
     }
     /// Removes a vertical seam from the image,
-    /// diminishing its width by 1.
+    /// diminishing its width by 1,
+    /// unless the [stop criterion](Options::stop_when) rejects it.
     pub fn remove_seam(&mut self) {
-        let img = &self.carved;
-        let seam = self.seam_finder.extract_seam(|p| energy_fn(img, p));
+        self.try_remove_seam();
+    }
+    /// Like [remove_seam](Carvable::remove_seam), but returns false, leaving the image unchanged,
+    /// if the [stop criterion](Options::stop_when) rejects the seam.
+    pub fn try_remove_seam(&mut self) -> bool {
+        self.restored.clear();
+        let seam = self.solver.extract_seam(&mut ImageEnergy(&self.carved));
+        let cost = seam_cost(&mut ImageEnergy(&self.carved), &seam);
+        if !allows_seam(self.stop, &self.costs, cost) {
+            self.solver.restore_seam(&seam);
+            return false;
+        }
         self.carved.remove_seam(&seam);
+        self.seams.push(Seam::from_positions(Axis::Vertical, &seam));
+        self.costs.push(cost);
+        true
+    }
+    /// Removes several vertical seams from the image,
+    /// diminishing its width by `count`.
+    ///
+    /// This gives the same result as calling [remove_seam](Carvable::remove_seam)
+    /// `count` times, but it is usually faster: removed pixels are only flagged,
+    /// and the internal data is compacted when enough of them are pending,
+    /// instead of being moved after each seam.
+    ///
+    /// If [Options::seams_per_pass] is larger than 1, several seams are found at once,
+    /// which is faster but gives a different result.
+    ///
+    /// With a [stop criterion](Options::stop_when), seams are removed one at a time,
+    /// until one is rejected.
+    pub fn remove_seams(&mut self, count: u32) {
+        self.restored.clear();
+        if self.stop.is_some() {
+            for _ in 0..count {
+                if !self.try_remove_seam() {
+                    break;
+                }
+            }
+            return;
+        }
+        if self.seams_per_pass > 1 {
+            let mut remaining = count;
+            while remaining > 0 {
+                let energy = &mut ImageEnergy(&self.carved);
+                let found = self.solver.extract_seams(energy, remaining.min(self.seams_per_pass));
+                remaining -= found.len() as u32;
+                for seam in found {
+                    self.costs.push(seam_cost(&mut ImageEnergy(&self.carved), &seam));
+                    self.carved.remove_seam(&seam);
+                    self.seams.push(Seam::from_positions(Axis::Vertical, &seam));
+                }
+            }
+            return;
+        }
+        for _ in 0..count {
+            let pending = PendingCarved(&self.carved);
+            let energy = &mut ImageEnergy(&pending);
+            let seam = self.solver.extract_seam_deferred(energy);
+            self.costs.push(seam_cost(energy, &seam));
+            self.carved.defer_seam_removal(&seam);
+            self.seams.push(Seam::from_positions(Axis::Vertical, &seam));
+        }
+        self.solver.compact();
+        self.carved.compact();
+    }
+    /// Puts back the most recently removed seam,
+    /// increasing the width by 1.
+    /// Returns false if no seam has been removed.
+    pub fn restore_seam(&mut self) -> bool {
+        match self.seams.pop() {
+            Some(seam) => {
+                let positions = seam.positions();
+                self.carved.restore_seam(&positions);
+                self.solver.restore_seam(&positions);
+                let cost = self.costs.pop().unwrap_or_default();
+                self.restored.push((seam, cost));
+                true
+            }
+            None => false,
+        }
+    }
+    /// Removes again the most recently restored seam, if nothing else changed since
+    fn redo_seam(&mut self) -> bool {
+        match self.restored.pop() {
+            Some((seam, cost)) => {
+                let positions = seam.positions();
+                self.solver.remove_seam(&positions);
+                self.carved.remove_seam(&positions);
+                self.seams.push(seam);
+                self.costs.push(cost);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Removes or restores seams until the image has the given width.
+    ///
+    /// The width cannot be larger than the one of the original image.
+    /// Restored seams do not need to be computed again, and the seams that were restored
+    /// are removed again when the width is reduced, unless other seams were removed
+    /// in between, so moving back and forth between widths is cheap,
+    /// and gives the same images each time.
+    ///
+    /// ```
+    /// use image::GenericImageView;
+    /// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+    /// let mut carvable = seamcarving::Carvable::new(&img);
+    /// carvable.set_width(12);
+    /// carvable.set_width(15);
+    /// assert_eq!(carvable.result().dimensions(), (15, 10));
+    /// ```
+    pub fn set_width(&mut self, width: u32) {
+        let width = width.min(self.carved.original().width());
+        while self.carved.width() > width && self.redo_seam() {}
+        if self.carved.width() > width {
+            self.remove_seams(self.carved.width() - width);
+        }
+        while self.carved.width() < width && self.restore_seam() {}
     }
     /// Get the resulting carved image
     pub fn result(&self) -> &Carved<'a, IMG> {
         &self.carved
     }
+    /// Returns the cost of each seam removed so far, in the order of their removal.
+    /// The cost of a seam is the sum of the current energies of its pixels when it is removed.
+    pub fn seam_costs(&self) -> &[u64] {
+        &self.costs
+    }
+    /// Returns the seams removed so far, that can be applied to other images using [replay]
+    pub fn plan(&self) -> CarvingPlan {
+        let Pos(width, height) = max_pos(self.carved.original());
+        CarvingPlan {
+            width,
+            height,
+            seams: self.seams.clone(),
+        }
+    }
 }
 
+#[cfg(feature = "image")]
 /// Converts [GenericImageView](GenericImageView)
 /// to an [ImageBuffer](ImageBuffer)
 pub fn image_view_to_buffer<IMG: GenericImageView>(
@@ -101,22 +606,16 @@ where
     ImageBuffer::from_fn(w, h, |x, y| img.get_pixel(x, y))
 }
 
-fn carve<IMG: GenericImageView>(img: &IMG, pixel_count: u32) -> Carved<IMG>
-where
-    <IMG as GenericImageView>::Pixel: 'static,
-{
-    let mut carvable = Carvable::new(img);
-    (0..pixel_count).for_each(|_| carvable.remove_seam());
-    carvable.carved
-}
-
+#[cfg(feature = "image")]
 #[cfg(test)]
 mod tests {
     use image::{GrayImage, ImageBuffer, Luma};
 
-    use crate::{energy_fn, Pos};
+    use crate::energy::energy_fn;
+    use crate::Pos;
 
     #[test]
+    #[allow(clippy::identity_op)]
     fn energy_fn_correct() {
         let img = GrayImage::from_raw(3, 2, vec![3, 1, 4, 1, 5, 9]).unwrap();
         let energy = ImageBuffer::from_fn(3, 2, |x, y| Luma([energy_fn(&img, Pos(x, y))]));
@@ -1,16 +1,39 @@
-use std::fmt::{Debug, Formatter};
-use std::ops::{Index, IndexMut};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+use core::ops::{Index, IndexMut};
 
 use crate::Pos;
 
+/// Deferred removals are compacted once they make up more than 1/256th of the rows
+const MAX_PENDING_DENSITY: usize = 256;
+
 pub(crate) struct Matrix<T> {
     original_width: usize,
     current_width: usize,
+    current_height: usize,
     contents: Vec<T>,
+    // The elements that have been removed but not compacted yet, allocated on the first one
+    pending: Option<Removals>,
+    pending_count: u32,
+}
+
+/// Flags marking the elements removed from the rows of a [Matrix] without being moved
+struct Removals {
+    // Number of 64-bit words of flags per row
+    words: usize,
+    // For each row, one bit per position in `contents`, set for the removed elements
+    flags: Vec<u64>,
+    // For each row and word of flags, the number of removed elements before that word
+    before: Vec<u32>,
+    // For each row, the positions of the removed elements, in the order of their removal
+    order: Vec<Vec<u32>>,
+    // For each row, the first and last removed positions
+    span: Vec<(u32, u32)>,
 }
 
 impl<T: Debug> Debug for Matrix<T> {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         writeln!(f, "Matrix {{")?;
         for line in self.contents.chunks_exact(self.original_width) {
             writeln!(f, "  {:?}", line)?;
@@ -28,12 +51,16 @@ impl<T> Matrix<T> {
         Matrix {
             original_width: width,
             current_width: width,
+            current_height: height,
             contents,
+            pending: None,
+            pending_count: 0,
         }
     }
 
     #[inline]
     pub fn remove_seam(&mut self, seam: &[Pos]) {
+        debug_assert_eq!(self.pending_count, 0, "remove_seam called before compact");
         let current_width = self.current_width;
         self.current_width -= 1;
         self.contents
@@ -46,6 +73,217 @@ impl<T> Matrix<T> {
                 }
             });
     }
+
+    /// Whether some removals have been deferred and not compacted yet
+    #[inline(always)]
+    pub fn has_pending(&self) -> bool {
+        self.pending_count > 0
+    }
+
+    /// Like indexing, but taking deferred removals into account.
+    ///
+    /// Plain indexing ignores them, because checking for them would slow down
+    /// every access, even when there are none.
+    #[inline(always)]
+    pub fn get_pending(&self, Pos(x, y): Pos) -> &T {
+        let (x, y) = (x as usize, y as usize);
+        &self.contents[self.stored_x(x, y) + y * self.original_width]
+    }
+
+    /// Mutable version of [get_pending](Matrix::get_pending)
+    #[inline(always)]
+    pub fn get_pending_mut(&mut self, Pos(x, y): Pos) -> &mut T {
+        let (x, y) = (x as usize, y as usize);
+        let x = self.stored_x(x, y);
+        &mut self.contents[x + y * self.original_width]
+    }
+
+    /// Position in `contents` of the element at the given position,
+    /// taking deferred removals into account
+    #[inline(always)]
+    fn stored_x(&self, x: usize, y: usize) -> usize {
+        match &self.pending {
+            Some(pending) if self.pending_count > 0 => {
+                pending.stored_x(x, y, self.pending_count as usize)
+            }
+            _ => x,
+        }
+    }
+
+    /// The current elements of a row
+    #[inline]
+    pub fn row(&self, y: usize) -> &[T] {
+        debug_assert_eq!(self.pending_count, 0, "row called before compact");
+        let start = y * self.original_width;
+        &self.contents[start..start + self.current_width]
+    }
+
+    /// The current elements of a row, mutably
+    #[inline]
+    pub fn row_mut(&mut self, y: usize) -> &mut [T] {
+        debug_assert_eq!(self.pending_count, 0, "row_mut called before compact");
+        let start = y * self.original_width;
+        &mut self.contents[start..start + self.current_width]
+    }
+
+    /// Removes a horizontal seam, given as positions in the transposed matrix,
+    /// from the last column to the first one.
+    #[inline]
+    pub fn remove_horizontal_seam(&mut self, seam: &[Pos]) {
+        debug_assert_eq!(self.pending_count, 0, "remove_horizontal_seam called before compact");
+        let width = self.original_width;
+        self.current_height -= 1;
+        for &Pos(y, x) in seam {
+            let (x, y) = (x as usize, y as usize);
+            for i in y..self.current_height {
+                self.contents.swap(x + i * width, x + (i + 1) * width);
+            }
+        }
+    }
+
+    /// Reverts [remove_seam](Matrix::remove_seam), given the same seam
+    #[inline]
+    pub fn restore_seam(&mut self, seam: &[Pos]) {
+        debug_assert_eq!(self.pending_count, 0, "restore_seam called before compact");
+        self.current_width += 1;
+        let current_width = self.current_width;
+        self.contents
+            .chunks_exact_mut(self.original_width)
+            .zip(seam.iter().rev())
+            .for_each(|(aliases, &Pos(x, _y))| {
+                let end = &mut aliases[x as usize..current_width];
+                if !end.is_empty() {
+                    end.rotate_right(1)
+                }
+            });
+    }
+}
+
+impl<T: Copy> Matrix<T> {
+    /// Removes a seam by flagging its elements, without moving any other element.
+    /// Until [compact](Matrix::compact) is called, elements must be accessed with
+    /// [get_pending](Matrix::get_pending), which is slower for the elements
+    /// that are between removed ones.
+    ///
+    /// The rows are compacted once the flagged elements make up a large enough part of them,
+    /// so that each element is moved once per compaction instead of once per seam,
+    /// while few enough elements are flagged for the accesses to stay fast.
+    pub fn defer_seam_removal(&mut self, seam: &[Pos]) {
+        let (width, height) = (self.original_width, self.current_height);
+        let pending = self.pending.get_or_insert_with(|| Removals::new(width, height));
+        for &Pos(x, y) in seam {
+            let (x, y) = (x as usize, y as usize);
+            let count = self.pending_count as usize;
+            let stored = if count > 0 { pending.stored_x(x, y, count) } else { x };
+            pending.remove(stored, y);
+        }
+        self.current_width -= 1;
+        self.pending_count += 1;
+        if self.pending_count as usize * MAX_PENDING_DENSITY > self.current_width {
+            self.compact();
+        }
+    }
+
+    /// Applies all the deferred removals, moving each element at most once.
+    ///
+    /// As with [remove_seam](Matrix::remove_seam), removed elements are kept after
+    /// the end of their row, the most recently removed one first.
+    pub fn compact(&mut self) {
+        let count = self.pending_count as usize;
+        let pending = match &mut self.pending {
+            Some(pending) if count > 0 => pending,
+            _ => return,
+        };
+        let old_width = self.current_width + count;
+        let mut removed = Vec::with_capacity(count);
+        let rows = self.contents.chunks_exact_mut(self.original_width);
+        for (y, row) in rows.enumerate().take(self.current_height) {
+            let flags = &mut pending.flags[y * pending.words..(y + 1) * pending.words];
+            removed.extend(pending.order[y].iter().rev().map(|&p| row[p as usize]));
+            // Move each run of kept elements left, past the removed ones before it
+            let (mut read, mut write) = (0, 0);
+            for (word, bits) in flags.iter_mut().enumerate() {
+                while *bits != 0 {
+                    let p = 64 * word + bits.trailing_zeros() as usize;
+                    if read != write {
+                        row.copy_within(read..p, write);
+                    }
+                    write += p - read;
+                    read = p + 1;
+                    *bits &= *bits - 1;
+                }
+            }
+            if read != write {
+                row.copy_within(read..old_width, write);
+            }
+            row[self.current_width..old_width].copy_from_slice(&removed);
+            removed.clear();
+            pending.order[y].clear();
+            pending.span[y] = (u32::MAX, 0);
+            pending.before[y * pending.words..(y + 1) * pending.words].fill(0);
+        }
+        self.pending_count = 0;
+    }
+}
+
+impl Removals {
+    fn new(width: usize, height: usize) -> Self {
+        let words = (width + 63) / 64;
+        Removals {
+            words,
+            flags: vec![0; words * height],
+            before: vec![0; words * height],
+            order: (0..height).map(|_| Vec::new()).collect(),
+            span: vec![(u32::MAX, 0); height],
+        }
+    }
+
+    /// Position in its row of the `x`-th element that is not removed,
+    /// given the number of removed elements of each row
+    #[inline(always)]
+    fn stored_x(&self, x: usize, y: usize, count: usize) -> usize {
+        let (first, last) = self.span[y];
+        if x < first as usize {
+            return x;
+        }
+        if x + count > last as usize {
+            return x + count;
+        }
+        let row = y * self.words;
+        let before = &self.before[row..row + self.words];
+        // Removed elements are sparse, so the word that contains it is at most
+        // a few words after the one it would be in without them
+        let mut word = x / 64;
+        while word + 1 < self.words && 64 * (word + 1) - before[word + 1] as usize <= x {
+            word += 1;
+        }
+        let rank = x + before[word] as usize - 64 * word;
+        64 * word + select_zero(self.flags[row + word], rank)
+    }
+
+    /// Flags the element at the given position of `contents` as removed
+    #[inline]
+    fn remove(&mut self, stored: usize, y: usize) {
+        let row = y * self.words;
+        self.flags[row + stored / 64] |= 1 << (stored % 64);
+        self.before[row + stored / 64 + 1..row + self.words]
+            .iter_mut()
+            .for_each(|count| *count += 1);
+        self.order[y].push(stored as u32);
+        let span = &mut self.span[y];
+        *span = (span.0.min(stored as u32), span.1.max(stored as u32));
+    }
+}
+
+/// Index of the `n`-th bit of `word` that is not set, counting from 0
+#[inline(always)]
+fn select_zero(mut word: u64, mut n: usize) -> usize {
+    // Few bits are set, so each of them up to the result can be skipped in turn
+    while word != 0 && word.trailing_zeros() as usize <= n {
+        n += 1;
+        word &= word - 1;
+    }
+    n
 }
 
 impl<T> Index<Pos> for Matrix<T> {
@@ -65,3 +303,71 @@ impl<T> IndexMut<Pos> for Matrix<T> {
         &mut self.contents[x + y * self.original_width]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::matrix::Matrix;
+    use crate::pos::Pos;
+
+    #[test]
+    fn deferred_removals() {
+        let seams = [
+            vec![Pos(1, 2), Pos(2, 1), Pos(3, 0)],
+            vec![Pos(1, 2), Pos(1, 1), Pos(0, 0)],
+            vec![Pos(2, 2), Pos(2, 1), Pos(2, 0)],
+        ];
+        let mut immediate = Matrix::from_fn(Pos(5, 3), |x, y| 10 * y + x);
+        let mut deferred = Matrix::from_fn(Pos(5, 3), |x, y| 10 * y + x);
+        for seam in &seams {
+            immediate.remove_seam(seam);
+            deferred.defer_seam_removal(seam);
+            for p in Pos::iter_in_rect(Pos(0, 0), Pos(immediate.current_width as u32, 3)) {
+                assert_eq!(immediate[p], *deferred.get_pending(p));
+            }
+        }
+        deferred.compact();
+        assert_eq!(deferred.contents, immediate.contents);
+        for seam in seams.iter().rev() {
+            immediate.restore_seam(seam);
+            deferred.restore_seam(seam);
+        }
+        assert_eq!(deferred.contents, (0..3).flat_map(|y| (0..5).map(move |x| 10 * y + x)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn deferred_removals_across_words() {
+        let size = Pos(6000, 3);
+        let mut immediate = Matrix::from_fn(size, |x, y| (x, y));
+        let mut deferred = Matrix::from_fn(size, |x, y| (x, y));
+        let mut state = 0x2545_f491_u32;
+        let mut random = |max: u32| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state % max
+        };
+        let mut seams = vec![];
+        for _ in 0..60 {
+            let width = immediate.current_width as u32;
+            let mut x = random(width);
+            let mut seam = vec![];
+            for y in 0..size.1 {
+                x = (x + random(3)).saturating_sub(1).min(width - 1);
+                seam.push(Pos(x, y));
+            }
+            seam.reverse();
+            immediate.remove_seam(&seam);
+            deferred.defer_seam_removal(&seam);
+            for p in Pos::iter_in_rect(Pos(0, 0), Pos(width - 1, size.1)) {
+                assert_eq!(immediate[p], *deferred.get_pending(p));
+            }
+            seams.push(seam);
+        }
+        deferred.compact();
+        assert_eq!(deferred.contents, immediate.contents);
+        for seam in seams.iter().rev() {
+            deferred.restore_seam(seam);
+        }
+        assert_eq!(deferred.contents, Matrix::from_fn(size, |x, y| (x, y)).contents);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::pos::Pos;
+
+/// Parameters of a seam carving operation.
+///
+/// The default options are the ones used by [resize](crate::resize).
+///
+/// ```
+/// let options = seamcarving::Options::default().max_stretch(0.25);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub(crate) max_stretch: f32,
+    pub(crate) max_step: u8,
+    pub(crate) tie_break: TieBreak,
+    pub(crate) solver: Solver,
+    pub(crate) seams_per_pass: u32,
+    pub(crate) pyramid_factor: u32,
+    pub(crate) stop: Option<StopCriterion>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_stretch: 0.5,
+            max_step: 1,
+            tie_break: TieBreak::Leftmost,
+            solver: Solver::DynamicProgramming,
+            seams_per_pass: 1,
+            pyramid_factor: 1,
+            stop: None,
+        }
+    }
+}
+
+impl Options {
+    /// Sets the maximal fraction of the current width (or height)
+    /// that can be added in a single enlargement step.
+    ///
+    /// Enlarging an image works by duplicating its lowest-energy seams.
+    /// Inserting too many seams at once makes the same region stretched over and over,
+    /// so larger enlargements are split into several steps,
+    /// each adding at most `fraction * current_width` seams.
+    ///
+    /// The fraction is clamped between 0 and 1, and at least one seam is added per step.
+    pub fn max_stretch(mut self, fraction: f32) -> Self {
+        self.max_stretch = fraction.clamp(0., 1.);
+        self
+    }
+
+    /// Sets how many pixels a seam can move horizontally from one row to the next.
+    ///
+    /// The default is `1`: each pixel of a seam touches the pixel of the previous row,
+    /// directly or diagonally. Larger values allow seams to follow the content
+    /// more freely, at the cost of a slower search and more visible breaks.
+    /// `0` only allows straight seams, which amounts to removing whole columns
+    /// (or rows), chosen by their content.
+    ///
+    /// Values above 127 are clamped.
+    /// The direction of each step is stored in the same 32 bits as the energy
+    /// of the seam, which saturates at `u32::MAX >> b`, where `b` is the number
+    /// of bits of `2 * max_step + 1`: 2^31 - 1 for straight seams, 2^30 - 1 by default,
+    /// and down to 2^24 - 1 for steps of 64 to 127. Seams whose energies are above
+    /// that are not told apart, which happens on tall images with strong edges
+    /// everywhere: a pixel of an RGBA image can have an energy of up to 520 200.
+    pub fn max_step(mut self, max_step: u8) -> Self {
+        self.max_step = max_step.min(i8::MAX as u8);
+        self
+    }
+
+    /// Sets how to choose between seams that have exactly the same energy.
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Sets the algorithm used to find the seams
+    pub fn solver(mut self, solver: Solver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    /// Sets how many seams can be removed after each computation of the seam energies.
+    ///
+    /// The default is `1`, which always removes the seam with the lowest energy.
+    /// Larger values make large reductions faster, but less accurate:
+    /// after finding the best seam, the following ones are found from the same energies,
+    /// without taking into account how removing the previous ones changed them,
+    /// and are deviated where they would cross a previous seam.
+    /// The `benchmark` bench measures this trade-off.
+    ///
+    /// Only the [DynamicProgramming](Solver::DynamicProgramming) solver supports this.
+    /// `0` is treated as `1`.
+    pub fn seams_per_pass(mut self, count: u32) -> Self {
+        self.seams_per_pass = count.max(1);
+        self
+    }
+
+    /// Finds the seams on a copy of the image downscaled by `factor`,
+    /// and then only refines them at full resolution, in narrow bands around the coarse seams.
+    ///
+    /// The default is `1`, which searches the whole image for every seam.
+    /// With larger factors, each seam of the small copy gives `factor` seams
+    /// of the full image, searched in a band `3 * factor` pixels wide.
+    /// This makes large reductions of large images faster, but the seams can miss
+    /// details that disappear in the small copy.
+    /// Takes precedence over [seams_per_pass](Options::seams_per_pass).
+    /// `0` is treated as `1`.
+    pub fn pyramid(mut self, factor: u32) -> Self {
+        self.pyramid_factor = factor.max(1);
+        self
+    }
+
+    /// Stops removing seams before the first one that the criterion rejects,
+    /// which leaves the image larger than requested when its content cannot be
+    /// reduced further without removing important details.
+    ///
+    /// Vertical and horizontal seams are checked separately: when vertical seams stop,
+    /// horizontal seams are still removed. The cost of each removed seam is reported
+    /// by [resize_with_costs](crate::resize_with_costs)
+    /// and [Carvable::seam_costs](crate::Carvable::seam_costs).
+    /// Only reductions are affected, not the seams duplicated to enlarge an image.
+    /// While a criterion is set, [Carvable](crate::Carvable) removes seams one at a time.
+    ///
+    /// ```
+    /// use seamcarving::{Options, StopCriterion};
+    /// let options = Options::default().stop_when(StopCriterion::MaxRatio(3.));
+    /// ```
+    pub fn stop_when(mut self, criterion: StopCriterion) -> Self {
+        self.stop = Some(criterion);
+        self
+    }
+}
+
+/// A condition on the cost of the next seam, that stops the removal of seams.
+///
+/// The cost of a seam is the sum of the current energies of its pixels when it is removed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopCriterion {
+    /// Stop before a seam that costs more than the given value
+    MaxCost(u64),
+    /// Stop before a seam that costs more than the given factor times the cost of the first seam
+    /// removed in the same direction. A first seam of cost 0 counts as a cost of 1.
+    MaxRatio(f64),
+}
+
+/// Whether a seam of the given cost can be removed,
+/// after seams of the `previous` costs were removed in the same direction
+pub(crate) fn allows_seam(stop: Option<StopCriterion>, previous: &[u64], cost: u64) -> bool {
+    match (stop, previous.first()) {
+        (None, _) => true,
+        (Some(StopCriterion::MaxCost(max)), _) => cost <= max,
+        (Some(StopCriterion::MaxRatio(_)), None) => true,
+        (Some(StopCriterion::MaxRatio(factor)), Some(&first)) => {
+            cost as f64 <= factor * first.max(1) as f64
+        }
+    }
+}
+
+/// Algorithm used to find the lowest-energy seams
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Solver {
+    /// Dynamic programming, reusing the computations made for the previous seams.
+    /// This is the default, and the fastest.
+    DynamicProgramming,
+    /// Minimum cut in a graph of the pixels.
+    ///
+    /// This is much slower, and ignores the [TieBreak] policy.
+    /// With `forward_energy`, the cost of a seam is the energy of the edges
+    /// that its removal creates between pixels that were not adjacent,
+    /// instead of the energy of the removed pixels.
+    /// This better preserves straight lines.
+    GraphCut {
+        /// Whether to use forward energy
+        forward_energy: bool,
+    },
+}
+
+/// Policy used to choose between several seams with the same energy.
+///
+/// Equal energies are frequent in flat regions, such as skies or walls.
+/// The policy is applied both when choosing where a seam ends
+/// and when choosing how it continues on the previous row.
+///
+/// Whatever the policy, resizing the same image with the same options
+/// always gives the same result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Prefer the leftmost pixel. This is the default,
+    /// but it tends to remove more pixels from the left of flat regions.
+    Leftmost,
+    /// Prefer the rightmost pixel
+    Rightmost,
+    /// Prefer the pixel that was closest to the middle of the original image
+    Center,
+    /// Choose pseudo-randomly, from the given seed
+    Random(u64),
+}
+
+impl TieBreak {
+    /// Returns a key that is lower for the positions that are preferred.
+    /// The keys of [Center](TieBreak::Center) and [Random](TieBreak::Random) must be computed
+    /// on positions in the original image, of width `width`, for the choices they make to stay
+    /// consistent while seams are removed: only the order of the columns is kept,
+    /// which is enough for [Leftmost](TieBreak::Leftmost) and [Rightmost](TieBreak::Rightmost).
+    #[inline]
+    pub(crate) fn rank(self, Pos(x, y): Pos, width: u32) -> u64 {
+        match self {
+            TieBreak::Leftmost => u64::from(x),
+            TieBreak::Rightmost => u64::from(u32::MAX - x),
+            TieBreak::Center => {
+                let distance = (2 * u64::from(x)).abs_diff(u64::from(width.saturating_sub(1)));
+                (distance << 32) | u64::from(x)
+            }
+            TieBreak::Random(seed) => {
+                let pos = (u64::from(x) << 32) | u64::from(y);
+                splitmix64(seed ^ splitmix64(pos))
+            }
+        }
+    }
+}
+
+/// A simple and well distributed hash function
+#[inline]
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
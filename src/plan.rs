@@ -0,0 +1,355 @@
+//! A serializable record of the seams removed from an image.
+//!
+//! ## Binary format
+//!
+//! [CarvingPlan::to_bytes] produces the following, where all integers are little-endian:
+//!
+//! | field        | encoding                                   |
+//! |--------------|--------------------------------------------|
+//! | magic        | the 4 bytes `SEAM`                         |
+//! | version      | 1 byte, currently `1`                      |
+//! | width        | u32                                        |
+//! | height       | u32                                        |
+//! | seam count   | u32                                        |
+//! | seams        | `seam count` seams, described below        |
+//!
+//! Each seam starts with its axis, as a single byte: `0` for vertical, `1` for horizontal.
+//! It is followed by its offsets. Their number is not stored: it is the height of the image
+//! (for vertical seams) or its width (for horizontal seams) at the time the seam is removed.
+//! The first offset is stored as an unsigned
+//! [LEB128](https://en.wikipedia.org/wiki/LEB128) integer,
+//! and each following offset as the zigzag-encoded LEB128 difference with the previous one.
+//! For seams with the default maximal step, every offset after the first takes a single byte.
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::pos::Pos;
+
+/// Orientation of a seam
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Axis {
+    /// A seam that goes from the top to the bottom of the image.
+    /// Removing it decreases the width by 1.
+    Vertical,
+    /// A seam that goes from the left to the right of the image.
+    /// Removing it decreases the height by 1.
+    Horizontal,
+}
+
+/// A seam removed from an image
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Seam {
+    /// Orientation of the seam
+    pub axis: Axis,
+    /// For a vertical seam, the x coordinate of the removed pixel on each row, from top to bottom.
+    /// For a horizontal seam, the y coordinate of the removed pixel on each column,
+    /// from left to right.
+    ///
+    /// Coordinates are relative to the image as it is when the seam is removed,
+    /// that is, after all the previous seams of the plan have been removed.
+    pub offsets: Vec<u32>,
+}
+
+impl Seam {
+    /// Converts positions in an image (or in a rotated image, for horizontal seams)
+    pub(crate) fn from_positions(axis: Axis, positions: &[Pos]) -> Self {
+        let mut offsets = vec![0; positions.len()];
+        for &Pos(x, y) in positions {
+            offsets[y as usize] = x;
+        }
+        Seam { axis, offsets }
+    }
+
+    /// Converts to positions, from the last row to the first one,
+    /// in the image (or in the rotated image, for horizontal seams)
+    pub(crate) fn positions(&self) -> Vec<Pos> {
+        let rows = self.offsets.iter().enumerate().rev();
+        rows.map(|(y, &x)| Pos(x, y as u32)).collect()
+    }
+}
+
+/// The sequence of seams removed from an image.
+///
+/// A plan can be obtained from [resize_with_plan](crate::resize_with_plan)
+/// or [Carvable::plan](crate::Carvable::plan), stored, and applied again
+/// to an image of the same size with [replay](crate::replay),
+/// without recomputing any energy.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CarvingPlan {
+    /// Width of the original image
+    pub width: u32,
+    /// Height of the original image
+    pub height: u32,
+    /// Removed seams, in the order of their removal
+    pub seams: Vec<Seam>,
+}
+
+const MAGIC: &[u8; 4] = b"SEAM";
+const VERSION: u8 = 1;
+
+impl CarvingPlan {
+    /// Dimensions of the image after all the seams of the plan are removed
+    pub fn final_dimensions(&self) -> (u32, u32) {
+        let vertical = self.seams.iter().filter(|s| s.axis == Axis::Vertical).count() as u32;
+        let horizontal = self.seams.len() as u32 - vertical;
+        (
+            self.width.saturating_sub(vertical),
+            self.height.saturating_sub(horizontal),
+        )
+    }
+
+    /// Checks that all the seams fit in the image they are removed from
+    pub fn validate(&self) -> Result<(), PlanError> {
+        let (mut width, mut height) = (self.width, self.height);
+        for seam in &self.seams {
+            let (len, max) = match seam.axis {
+                Axis::Vertical => (height, width),
+                Axis::Horizontal => (width, height),
+            };
+            if seam.offsets.len() != len as usize || seam.offsets.iter().any(|&o| o >= max) {
+                return Err(PlanError::InvalidSeam);
+            }
+            let shrunk = match seam.axis {
+                Axis::Vertical => &mut width,
+                Axis::Horizontal => &mut height,
+            };
+            *shrunk = shrunk.checked_sub(1).ok_or(PlanError::InvalidSeam)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the plan in the compact binary format described in the [module documentation](self)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(17 + self.seams.len() * (self.height as usize + 5));
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        for &n in &[self.width, self.height, self.seams.len() as u32] {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        for seam in &self.seams {
+            bytes.push(match seam.axis {
+                Axis::Vertical => 0,
+                Axis::Horizontal => 1,
+            });
+            let mut previous = None;
+            for &offset in &seam.offsets {
+                match previous {
+                    None => write_varint(&mut bytes, offset),
+                    Some(p) => write_varint(&mut bytes, zigzag(offset as i64 - p as i64)),
+                }
+                previous = Some(offset);
+            }
+        }
+        bytes
+    }
+
+    /// Decodes and validates a plan encoded with [to_bytes](CarvingPlan::to_bytes)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PlanError> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err(PlanError::InvalidMagic);
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(PlanError::UnsupportedVersion(version));
+        }
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let seam_count = reader.u32()?;
+        let (mut current_width, mut current_height) = (width, height);
+        // Each seam and each offset takes at least one byte, which bounds the allocations
+        let max_seams = seam_count.min(width.saturating_add(height)) as usize;
+        let mut seams = Vec::with_capacity(max_seams.min(reader.0.len()));
+        for _ in 0..seam_count {
+            let (axis, len) = match reader.take(1)?[0] {
+                0 if current_width > 0 => {
+                    current_width -= 1;
+                    (Axis::Vertical, current_height)
+                }
+                1 if current_height > 0 => {
+                    current_height -= 1;
+                    (Axis::Horizontal, current_width)
+                }
+                _ => return Err(PlanError::InvalidSeam),
+            };
+            if reader.0.len() < len as usize {
+                return Err(PlanError::Truncated);
+            }
+            let mut offsets = Vec::with_capacity(len as usize);
+            for i in 0..len {
+                let value = reader.varint()?;
+                let offset = if i == 0 {
+                    i64::from(value)
+                } else {
+                    i64::from(offsets[i as usize - 1]) + unzigzag(value)
+                };
+                offsets.push(offset.try_into().map_err(|_| PlanError::InvalidSeam)?);
+            }
+            seams.push(Seam { axis, offsets });
+        }
+        if !reader.0.is_empty() {
+            return Err(PlanError::TrailingBytes);
+        }
+        let plan = CarvingPlan { width, height, seams };
+        plan.validate()?;
+        Ok(plan)
+    }
+}
+
+/// An error that occurs when reading or applying a [CarvingPlan]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanError {
+    /// The data does not start with the expected magic bytes
+    InvalidMagic,
+    /// The data was encoded with an unknown version of the format
+    UnsupportedVersion(u8),
+    /// The data ends before the end of the plan
+    Truncated,
+    /// There is data after the end of the plan
+    TrailingBytes,
+    /// A seam does not fit in the image it is removed from
+    InvalidSeam,
+    /// The plan was made for an image of a different size
+    SizeMismatch,
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PlanError::InvalidMagic => write!(f, "not a carving plan"),
+            PlanError::UnsupportedVersion(v) => write!(f, "unsupported carving plan version {}", v),
+            PlanError::Truncated => write!(f, "truncated carving plan"),
+            PlanError::TrailingBytes => write!(f, "unexpected data after the carving plan"),
+            PlanError::InvalidSeam => write!(f, "a seam does not fit in the image"),
+            PlanError::SizeMismatch => write!(f, "the plan was made for an image of another size"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PlanError {}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], PlanError> {
+        if self.0.len() < n {
+            return Err(PlanError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, PlanError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn varint(&mut self) -> Result<u32, PlanError> {
+        let mut value: u64 = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return value.try_into().map_err(|_| PlanError::InvalidSeam);
+            }
+        }
+        Err(PlanError::InvalidSeam)
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn zigzag(n: i64) -> u32 {
+    ((n << 1) ^ (n >> 63)) as u32
+}
+
+fn unzigzag(n: u32) -> i64 {
+    i64::from(n >> 1) ^ -i64::from(n & 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plan::{unzigzag, zigzag, Axis, CarvingPlan, PlanError, Seam};
+
+    fn plan() -> CarvingPlan {
+        CarvingPlan {
+            width: 300,
+            height: 2,
+            seams: vec![
+                Seam {
+                    axis: Axis::Vertical,
+                    offsets: vec![299, 150],
+                },
+                Seam {
+                    axis: Axis::Horizontal,
+                    offsets: (0..299).map(|x| x % 2).collect(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn zigzag_roundtrip() {
+        for &n in &[0, 1, -1, 127, -128, 1 << 20, -(1 << 20)] {
+            assert_eq!(unzigzag(zigzag(n)), n);
+        }
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        let bytes = plan().to_bytes();
+        assert_eq!(CarvingPlan::from_bytes(&bytes), Ok(plan()));
+        assert_eq!(plan().final_dimensions(), (299, 1));
+    }
+
+    #[test]
+    fn invalid_binary() {
+        let bytes = plan().to_bytes();
+        assert_eq!(CarvingPlan::from_bytes(&bytes[..bytes.len() - 1]), Err(PlanError::Truncated));
+        assert_eq!(CarvingPlan::from_bytes(b"JUNK"), Err(PlanError::InvalidMagic));
+        let mut invalid = plan();
+        invalid.seams[0].offsets[0] = 300;
+        assert_eq!(CarvingPlan::from_bytes(&invalid.to_bytes()), Err(PlanError::InvalidSeam));
+    }
+
+    #[test]
+    fn huge_dimensions_are_not_allocated() {
+        // A 1 pixel wide and 2^32 - 1 pixels high image, with a seam, but no offsets
+        let mut bytes = b"SEAM\x01".to_vec();
+        for &n in &[1, u32::MAX, 1] {
+            bytes.extend_from_slice(&u32::to_le_bytes(n));
+        }
+        bytes.push(0);
+        assert_eq!(bytes.len(), 18);
+        assert_eq!(CarvingPlan::from_bytes(&bytes), Err(PlanError::Truncated));
+    }
+
+    #[test]
+    fn seams_of_empty_images_are_invalid() {
+        let plan = CarvingPlan {
+            width: 0,
+            height: 0,
+            seams: vec![Seam {
+                axis: Axis::Vertical,
+                offsets: vec![],
+            }],
+        };
+        assert_eq!(plan.validate(), Err(PlanError::InvalidSeam));
+    }
+}
@@ -1,28 +1,36 @@
-use std::ops::{Sub, Add};
+use core::convert::TryFrom;
+use core::ops::{Sub, Add};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub(crate) struct Pos(pub u32, pub u32);
 
+/// A position stored on 16 bits per coordinate
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct Pos16(u16, u16);
+
 impl Pos {
-    pub fn successors(self, size: Pos) -> PosLine {
+    /// Positions on the next line that are at most `max_step` pixels away horizontally
+    pub fn successors(self, size: Pos, max_step: u32) -> PosLine {
         let Pos(x0, y0) = self;
-        let x_end = (x0 + 1).min(size.0 - 1);
+        let x_end = (x0 + max_step).min(size.0 - 1);
         let y = y0 + 1;
         let (x, y) = if y < size.1 {
-            (x0.saturating_sub(1), y)
+            (x0.saturating_sub(max_step), y)
         } else { (x_end + 1, y0) };
         PosLine { x, y, x_end }
     }
 
-    pub fn predecessors(self, size: Pos) -> PosLine {
+    /// Positions on the previous line that are at most `max_step` pixels away horizontally
+    pub fn predecessors(self, size: Pos, max_step: u32) -> PosLine {
         let Pos(x0, y0) = self;
-        let x_end = (x0 + 1).min(size.0 - 1);
+        let x_end = (x0 + max_step).min(size.0 - 1);
         let (x, y) = if let Some(y) = y0.checked_sub(1) {
-            (x0.saturating_sub(1), y)
+            (x0.saturating_sub(max_step), y)
         } else { (x_end + 1, 0) };
         PosLine { x, y, x_end }
     }
 
+    #[cfg(test)]
     pub fn iter_in_rect(start: Pos, end: Pos) -> RectIterator {
         RectIterator { current: start, start, end }
     }
@@ -54,12 +62,14 @@ impl Iterator for PosLine {
     }
 }
 
+#[cfg(test)]
 pub(crate) struct RectIterator {
     current: Pos,
     start: Pos,
     end: Pos,
 }
 
+#[cfg(test)]
 impl Iterator for RectIterator {
     type Item = Pos;
 
@@ -96,3 +106,19 @@ impl Sub<Pos> for Pos {
         Pos(self.0.saturating_sub(rhs.0), self.1.saturating_sub(rhs.1))
     }
 }
+
+impl From<Pos16> for Pos {
+    #[inline(always)]
+    fn from(Pos16(x, y): Pos16) -> Self {
+        Pos(u32::from(x), u32::from(y))
+    }
+}
+
+impl TryFrom<Pos> for Pos16 {
+    type Error = core::num::TryFromIntError;
+
+    #[inline(always)]
+    fn try_from(Pos(x, y): Pos) -> Result<Self, Self::Error> {
+        Ok(Pos16(u16::try_from(x)?, u16::try_from(y)?))
+    }
+}
@@ -0,0 +1,132 @@
+use std::ops::Range;
+
+use image::imageops::{resize, FilterType};
+use image::GenericImageView;
+
+use crate::energy::energy_fn;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+use crate::{max_pos, Carved, Options};
+
+/// Finds `count` vertical seams to remove from an image, using a downscaled copy
+/// to restrict the search at full resolution to narrow bands around the coarse seams.
+///
+/// Each seam found on the copy reduced by `factor` delimits a band of `3 * factor` columns,
+/// from which `factor` seams are removed at full resolution.
+/// The seams are given in the coordinates of the image after the previous ones are removed.
+pub(crate) fn find_seams<IMG: GenericImageView>(
+    img: &IMG,
+    count: u32,
+    factor: u32,
+    options: &Options,
+) -> Vec<Vec<Pos>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(width, height) = max_pos(img);
+    if width == 0 || height == 0 {
+        return vec![Vec::new(); count as usize];
+    }
+    let count = count.min(width);
+    let coarse_size = Pos(div_ceil(width, factor), div_ceil(height, factor));
+    let coarse_seams = coarse_seams(img, coarse_size, div_ceil(count, factor), options);
+    let mut carved = Carved::new(img);
+    let mut finder = SeamFinder::new(max_pos(img), options);
+    let mut seams = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let coarse_seam = &coarse_seams[(i / factor) as usize];
+        let columns = bands(&carved, coarse_seam, factor, u32::from(options.max_step));
+        let seam = finder
+            .extract_seam_in(&columns, |p| energy_fn(&carved, p))
+            .expect("the bands are connected");
+        carved.remove_seam(&seam);
+        seams.push(seam);
+    }
+    seams
+}
+
+/// Carves a downscaled copy of the image.
+/// For each seam, returns the column of its pixel on each row of the copy,
+/// in the coordinates of the copy before any seam was removed.
+fn coarse_seams<IMG: GenericImageView>(
+    img: &IMG,
+    Pos(width, height): Pos,
+    count: u32,
+    options: &Options,
+) -> Vec<Vec<u32>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let coarse = resize(img, width, height, FilterType::Triangle);
+    let mut carved = Carved::new(&coarse);
+    let mut finder = SeamFinder::new(Pos(width, height), options);
+    (0..count.min(width))
+        .map(|_| {
+            let seam = finder.extract_seam(|p| energy_fn(&carved, p));
+            let mut columns = vec![0; height as usize];
+            for &pos in &seam {
+                columns[pos.1 as usize] = carved.transform_pos(pos).0;
+            }
+            carved.remove_seam(&seam);
+            columns
+        })
+        .collect()
+}
+
+/// For each row of the image, the columns that correspond to the pixels of the coarse seam
+/// and of their left and right neighbours, in the current coordinates of the carved image.
+///
+/// The coarse seam is connected in the coordinates of the downscaled copy,
+/// but not necessarily in the original ones, so bands are extended where needed
+/// for a seam to go from each row to the next one.
+fn bands<IMG: GenericImageView>(
+    carved: &Carved<IMG>,
+    coarse_seam: &[u32],
+    factor: u32,
+    max_step: u32,
+) -> Vec<Range<u32>> {
+    let Pos(width, height) = max_pos(carved);
+    let last_row = coarse_seam.len() - 1;
+    let mut bands = Vec::with_capacity(height as usize);
+    // The columns of the current row that a seam can reach from the top row
+    let mut reachable = 0..width;
+    for y in 0..height {
+        let x = coarse_seam[((y / factor) as usize).min(last_row)];
+        let (start, end) = (x.saturating_sub(1) * factor, (x + 2) * factor);
+        // Aliases are increasing on each row
+        let mut first = partition_point(width, |x| carved.transform_pos(Pos(x, y)).0 < start);
+        let mut last = partition_point(width, |x| carved.transform_pos(Pos(x, y)).0 < end);
+        if first == last {
+            // All the pixels of the band were removed: use the closest remaining one
+            first = first.min(width - 1);
+            last = first + 1;
+        }
+        let reach_start = reachable.start.saturating_sub(max_step);
+        let reach_end = (reachable.end + max_step).min(width);
+        // Keep at least the closest column the previous rows can reach
+        let first = first.min(reach_end - 1);
+        let last = last.max(reach_start + 1);
+        reachable = first.max(reach_start)..last.min(reach_end);
+        bands.push(first..last);
+    }
+    bands
+}
+
+/// The first x in `0..width` for which `pred` is false, if it is true and then false
+fn partition_point(width: u32, pred: impl Fn(u32) -> bool) -> u32 {
+    let (mut low, mut high) = (0, width);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// `a / b` rounded up, without overflowing
+fn div_ceil(a: u32, b: u32) -> u32 {
+    a / b + u32::from(a % b != 0)
+}
@@ -0,0 +1,502 @@
+//! Seam carving on images stored in plain slices, without depending on the `image` crate.
+//!
+//! This is useful for frames that come from other libraries, such as video decoders
+//! or GPU readbacks. The functions of the crate root that take
+//! [GenericImageView](https://docs.rs/image/0.23/image/trait.GenericImageView.html)s
+//! are only available with the `image` feature, which is enabled by default.
+//!
+//! ```
+//! use seamcarving::raw::{resize_raw, RawImage};
+//! // A 4x2 RGB image, with 2 unused elements at the end of each row
+//! let data: Vec<u8> = vec![
+//!     9, 9, 9, 0, 0, 0, 1, 1, 1, 9, 9, 9, 42, 42, //
+//!     9, 9, 9, 0, 0, 0, 1, 1, 1, 9, 9, 9, 42, 42, //
+//! ];
+//! let img = RawImage::new(&data, 4, 2, 14, 3).unwrap();
+//! let (carved, plan) = resize_raw(&img, 3, 2, &Default::default());
+//! assert_eq!(carved, vec![9, 9, 9, 1, 1, 1, 9, 9, 9, 9, 9, 9, 1, 1, 1, 9, 9, 9]);
+//! assert_eq!(plan.final_dimensions(), (3, 2));
+//! ```
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use num_traits::ToPrimitive;
+
+use crate::aliases::{fits_u16, PosAliases};
+use crate::energy::square_diff;
+use crate::plan::{Axis, CarvingPlan, Seam};
+use crate::pos::Pos;
+use crate::solver::{seam_cost, AnySolver, EnergyMap, SeamSolver};
+use crate::options::allows_seam;
+use crate::{Options, StopCriterion};
+
+/// An image stored row by row in a slice, with the channels of each pixel next to each other
+#[derive(Clone, Copy, Debug)]
+pub struct RawImage<'a, T> {
+    data: &'a [T],
+    width: u32,
+    height: u32,
+    stride: usize,
+    channels: usize,
+}
+
+impl<'a, T> RawImage<'a, T> {
+    /// Describes an image of `width` by `height` pixels of `channels` elements each.
+    /// `stride` is the number of elements between the starts of two consecutive rows:
+    /// it is `width * channels` for images without padding.
+    pub fn new(
+        data: &'a [T],
+        width: u32,
+        height: u32,
+        stride: usize,
+        channels: usize,
+    ) -> Result<Self, RawImageError> {
+        let row_len = width as usize * channels;
+        if channels == 0 {
+            return Err(RawImageError::NoChannels);
+        }
+        if stride < row_len {
+            return Err(RawImageError::StrideTooSmall);
+        }
+        let len = match height {
+            0 => 0,
+            h => (h as usize - 1) * stride + row_len,
+        };
+        if data.len() < len {
+            return Err(RawImageError::DataTooShort);
+        }
+        Ok(RawImage {
+            data,
+            width,
+            height,
+            stride,
+            channels,
+        })
+    }
+
+    /// The width and the height of the image
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The channels of a pixel
+    #[inline(always)]
+    pub fn pixel(&self, x: u32, y: u32) -> &'a [T] {
+        let start = y as usize * self.stride + x as usize * self.channels;
+        &self.data[start..start + self.channels]
+    }
+}
+
+/// An error in the layout given to [RawImage::new]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RawImageError {
+    /// Pixels must have at least one channel
+    NoChannels,
+    /// The stride is smaller than a row
+    StrideTooSmall,
+    /// The slice is too short for the given dimensions
+    DataTooShort,
+}
+
+impl Display for RawImageError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            RawImageError::NoChannels => write!(f, "pixels have no channels"),
+            RawImageError::StrideTooSmall => write!(f, "the stride is smaller than a row"),
+            RawImageError::DataTooShort => write!(f, "the data is too short for the image size"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RawImageError {}
+
+/// How the energy of the pixels of a [RawImage] is computed.
+///
+/// Energies given for the pixels of the original image must have its size,
+/// and are carved along with it.
+#[derive(Clone, Copy, Debug)]
+pub enum EnergySource<'a> {
+    /// The squared differences between the neighbours of each pixel, as in [energy_map_raw]
+    Gradient,
+    /// The given energy of each pixel
+    Custom(RawImage<'a, u32>),
+    /// The gradient energy plus the given energy of each pixel,
+    /// for instance to protect some pixels from removal
+    Biased(RawImage<'a, u32>),
+}
+
+/// The energy of each pixel of an image, row by row, as used to find the seams to remove
+pub fn energy_map_raw<T: Copy + ToPrimitive>(img: &RawImage<T>) -> Vec<u32> {
+    let view = RawCarved::new(*img, EnergySource::Gradient);
+    let Pos(width, height) = view.size;
+    let mut energies = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        energies.extend((0..width).map(|x| view.energy(Pos(x, y))));
+    }
+    energies
+}
+
+/// Resizes an image to a lower width and height, removing vertical and then horizontal seams,
+/// like [resize_with_plan](crate::resize_with_plan).
+///
+/// Returns the carved image, with its rows next to each other, without padding,
+/// and the seams that were removed.
+/// Dimensions larger than the ones of the image are left unchanged,
+/// and a [stop criterion](Options::stop_when) can leave the image larger than requested.
+/// The [pyramid](Options::pyramid) option is ignored.
+pub fn resize_raw<T: Copy + ToPrimitive>(
+    img: &RawImage<T>,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> (Vec<T>, CarvingPlan) {
+    resize_raw_with_energy(img, EnergySource::Gradient, width, height, options)
+}
+
+/// Like [resize_raw], with the energy of the pixels computed as given
+///
+/// # Panics
+/// If an energy map given in `energy` does not have the size of the image
+pub fn resize_raw_with_energy<T: Copy + ToPrimitive>(
+    img: &RawImage<T>,
+    energy: EnergySource,
+    width: u32,
+    height: u32,
+    options: &Options,
+) -> (Vec<T>, CarvingPlan) {
+    let size = Pos(img.width, img.height);
+    let Pos(to_remove_x, to_remove_y) = size - Pos(width, height);
+    let mut view = RawCarved::new(*img, energy);
+    let mut seams = Vec::with_capacity((to_remove_x + to_remove_y) as usize);
+    let mut costs = Vec::with_capacity((to_remove_x + to_remove_y) as usize);
+    let (per_pass, stop) = (options.seams_per_pass, options.stop);
+    let mut solver = AnySolver::new(size, options);
+    let mut remaining = to_remove_x;
+    'vertical: while remaining > 0 {
+        let energy = &mut RawEnergy::Vertical(&view);
+        let found = solver.extract_seams(energy, remaining.min(per_pass));
+        remaining -= found.len() as u32;
+        for seam in found {
+            let cost = seam_cost(&mut RawEnergy::Vertical(&view), &seam);
+            if !allows_seam(stop, &costs, cost) {
+                break 'vertical;
+            }
+            view.pos_aliases.remove_seam(&seam);
+            view.size.0 -= 1;
+            seams.push(Seam::from_positions(Axis::Vertical, &seam));
+            costs.push(cost);
+        }
+    }
+    let vertical = costs.len();
+    let mut solver = AnySolver::new(Pos(view.size.1, view.size.0), options);
+    let mut remaining = to_remove_y;
+    'horizontal: while remaining > 0 {
+        let energy = &mut RawEnergy::Horizontal(&view);
+        let found = solver.extract_seams(energy, remaining.min(per_pass));
+        remaining -= found.len() as u32;
+        for seam in found {
+            let cost = seam_cost(&mut RawEnergy::Horizontal(&view), &seam);
+            if !allows_seam(stop, &costs[vertical..], cost) {
+                break 'horizontal;
+            }
+            view.pos_aliases.remove_horizontal_seam(&seam);
+            view.size.1 -= 1;
+            seams.push(Seam::from_positions(Axis::Horizontal, &seam));
+            costs.push(cost);
+        }
+    }
+    let plan = CarvingPlan {
+        width: size.0,
+        height: size.1,
+        seams,
+    };
+    (view.pixels(), plan)
+}
+
+/// A structure that allows removing vertical seams from a [RawImage] one at a time,
+/// like [Carvable](crate::Carvable)
+pub struct RawCarvable<'a, T> {
+    carved: RawCarved<'a, T>,
+    solver: AnySolver,
+    seams: Vec<Seam>,
+    costs: Vec<u64>,
+    // Seams put back by restore_seam, with their costs, removed again first by set_width
+    restored: Vec<(Seam, u64)>,
+    stop: Option<StopCriterion>,
+}
+
+impl<'a, T: Copy + ToPrimitive> RawCarvable<'a, T> {
+    /// Prepares the removal of seams from an image, with the energy computed as given.
+    /// The image itself is untouched.
+    ///
+    /// # Panics
+    /// If an energy map given in `energy` does not have the size of the image
+    pub fn new(img: RawImage<'a, T>, energy: EnergySource<'a>, options: &Options) -> Self {
+        let carved = RawCarved::new(img, energy);
+        let solver = AnySolver::new(carved.size, options);
+        RawCarvable {
+            carved,
+            solver,
+            seams: Vec::new(),
+            costs: Vec::new(),
+            restored: Vec::new(),
+            stop: options.stop,
+        }
+    }
+
+    /// Removes the lowest-energy vertical seam, diminishing the width by 1.
+    ///
+    /// Returns the positions of its pixels in the original image, from the top row
+    /// to the bottom one, or `None` if the image is empty or if the
+    /// [stop criterion](Options::stop_when) rejects the seam.
+    pub fn remove_seam(&mut self) -> Option<Vec<(u32, u32)>> {
+        let Pos(width, height) = self.carved.size;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        self.restored.clear();
+        let seam = self.solver.extract_seam(&mut RawEnergy::Vertical(&self.carved));
+        let cost = seam_cost(&mut RawEnergy::Vertical(&self.carved), &seam);
+        if !allows_seam(self.stop, &self.costs, cost) {
+            self.solver.restore_seam(&seam);
+            return None;
+        }
+        let original = seam
+            .iter()
+            .rev()
+            .map(|&pos| {
+                let Pos(x, y) = self.carved.pos_aliases.get(pos);
+                (x, y)
+            })
+            .collect();
+        self.carved.pos_aliases.remove_seam(&seam);
+        self.carved.size.0 -= 1;
+        self.seams.push(Seam::from_positions(Axis::Vertical, &seam));
+        self.costs.push(cost);
+        Some(original)
+    }
+
+    /// Puts back the most recently removed seam, increasing the width by 1.
+    /// Returns false if no seam has been removed.
+    pub fn restore_seam(&mut self) -> bool {
+        match self.seams.pop() {
+            Some(seam) => {
+                let positions = seam.positions();
+                self.carved.pos_aliases.restore_seam(&positions);
+                self.solver.restore_seam(&positions);
+                self.carved.size.0 += 1;
+                let cost = self.costs.pop().unwrap_or_default();
+                self.restored.push((seam, cost));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes again the most recently restored seam, if nothing else changed since
+    fn redo_seam(&mut self) -> bool {
+        match self.restored.pop() {
+            Some((seam, cost)) => {
+                let positions = seam.positions();
+                self.solver.remove_seam(&positions);
+                self.carved.pos_aliases.remove_seam(&positions);
+                self.carved.size.0 -= 1;
+                self.seams.push(seam);
+                self.costs.push(cost);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes or restores seams until the image has the given width,
+    /// like [Carvable::set_width](crate::Carvable::set_width)
+    pub fn set_width(&mut self, width: u32) {
+        let width = width.min(self.carved.img.width);
+        while self.carved.size.0 > width && self.redo_seam() {}
+        while self.carved.size.0 > width && self.remove_seam().is_some() {}
+        while self.carved.size.0 < width && self.restore_seam() {}
+    }
+
+    /// The current width and height of the image
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.carved.size.0, self.carved.size.1)
+    }
+
+    /// The carved image, with its rows next to each other, without padding
+    pub fn result(&self) -> Vec<T> {
+        self.carved.pixels()
+    }
+
+    /// The cost of each seam removed so far,
+    /// like [Carvable::seam_costs](crate::Carvable::seam_costs)
+    pub fn seam_costs(&self) -> &[u64] {
+        &self.costs
+    }
+
+    /// Returns the seams removed so far
+    pub fn plan(&self) -> CarvingPlan {
+        CarvingPlan {
+            width: self.carved.img.width,
+            height: self.carved.img.height,
+            seams: self.seams.clone(),
+        }
+    }
+}
+
+/// A [RawImage] with some seams removed, like [Carved2D](crate::Carved2D)
+struct RawCarved<'a, T> {
+    img: RawImage<'a, T>,
+    energy: EnergySource<'a>,
+    size: Pos,
+    pos_aliases: PosAliases,
+}
+
+impl<'a, T: Copy + ToPrimitive> RawCarved<'a, T> {
+    fn new(img: RawImage<'a, T>, energy: EnergySource<'a>) -> Self {
+        let size = Pos(img.width, img.height);
+        if let EnergySource::Custom(map) | EnergySource::Biased(map) = energy {
+            assert_eq!(
+                map.dimensions(),
+                img.dimensions(),
+                "The energy map must have the size of the image"
+            );
+        }
+        let narrow = fits_u16(size.0) && fits_u16(size.1);
+        let pos_aliases = PosAliases::from_fn(size, narrow, |x, y| Pos(x as u32, y as u32));
+        RawCarved {
+            img,
+            energy,
+            size,
+            pos_aliases,
+        }
+    }
+
+    #[inline(always)]
+    fn pixel(&self, pos: Pos) -> &'a [T] {
+        let Pos(x, y) = self.pos_aliases.get(pos);
+        self.img.pixel(x, y)
+    }
+
+    /// The current pixels, row by row
+    fn pixels(&self) -> Vec<T> {
+        let Pos(width, height) = self.size;
+        let mut data = Vec::with_capacity(width as usize * height as usize * self.img.channels);
+        for y in 0..height {
+            for x in 0..width {
+                data.extend_from_slice(self.pixel(Pos(x, y)));
+            }
+        }
+        data
+    }
+
+    #[inline(always)]
+    fn distance(&self, a: Pos, b: Pos) -> u32 {
+        let (a, b) = (self.pixel(a), self.pixel(b));
+        a.iter().zip(b).map(|(&a, &b)| square_diff(a, b)).sum()
+    }
+
+    /// The same gradient energy as for images of the `image` crate
+    #[inline(always)]
+    fn gradient(&self, pos: Pos) -> u32 {
+        let [top, bottom, left, right] = pos.surrounding(self.size);
+        self.distance(top, bottom) + self.distance(left, right)
+    }
+
+    #[inline(always)]
+    fn energy(&self, pos: Pos) -> u32 {
+        let given = |map: &RawImage<u32>| {
+            let Pos(x, y) = self.pos_aliases.get(pos);
+            map.pixel(x, y)[0]
+        };
+        match &self.energy {
+            EnergySource::Gradient => self.gradient(pos),
+            EnergySource::Custom(map) => given(map),
+            EnergySource::Biased(map) => self.gradient(pos).saturating_add(given(map)),
+        }
+    }
+}
+
+/// The energy of a [RawCarved] image, for finding vertical seams,
+/// or horizontal seams in transposed coordinates
+enum RawEnergy<'b, 'a, T> {
+    Vertical(&'b RawCarved<'a, T>),
+    Horizontal(&'b RawCarved<'a, T>),
+}
+
+impl<'b, 'a, T: Copy + ToPrimitive> EnergyMap for RawEnergy<'b, 'a, T> {
+    #[inline(always)]
+    fn energy(&mut self, pos: Pos) -> u32 {
+        match self {
+            RawEnergy::Vertical(view) => view.energy(pos),
+            RawEnergy::Horizontal(view) => view.energy(Pos(pos.1, pos.0)),
+        }
+    }
+
+    #[inline(always)]
+    fn distance(&mut self, a: Pos, b: Pos) -> u32 {
+        match self {
+            RawEnergy::Vertical(view) => view.distance(a, b),
+            RawEnergy::Horizontal(view) => view.distance(Pos(a.1, a.0), Pos(b.1, b.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_energy() {
+        let data: Vec<u8> = (0..20).map(|i| (i * 37 % 11) as u8).collect();
+        let img = RawImage::new(&data, 5, 4, 5, 1).unwrap();
+        // The lowest energy path goes down column 3, then column 2 on the last row
+        let energies: Vec<u32> = (0..20).map(|i| if i % 5 == 3 || i == 17 { 0 } else { 9 }).collect();
+        let map = RawImage::new(&energies, 5, 4, 5, 1).unwrap();
+        let (carved, plan) = resize_raw_with_energy(&img, EnergySource::Custom(map), 4, 4, &Options::default());
+        assert_eq!(plan.seams[0].offsets, vec![3, 3, 3, 2]);
+        let mut expected = data.clone();
+        for i in [17, 13, 8, 3] {
+            expected.remove(i);
+        }
+        assert_eq!(carved, expected);
+
+        let mut carvable = RawCarvable::new(img, EnergySource::Custom(map), &Options::default());
+        assert_eq!(carvable.remove_seam(), Some(vec![(3, 0), (3, 1), (3, 2), (2, 3)]));
+        assert_eq!(carvable.result(), expected);
+        assert_eq!(carvable.plan(), plan);
+        // The next seam is given in the coordinates of the original image
+        let seam = carvable.remove_seam().unwrap();
+        assert!(seam.iter().all(|&(x, y)| x != 3 || y == 3));
+        assert_eq!(carvable.dimensions(), (3, 4));
+    }
+
+    #[test]
+    fn biased_energy_protects_pixels() {
+        let data = vec![7u8; 12];
+        let img = RawImage::new(&data, 4, 3, 4, 1).unwrap();
+        assert_eq!(energy_map_raw(&img), vec![0; 12]);
+        // Every column but the last one is protected
+        let bias: Vec<u32> = (0..12).map(|i| if i % 4 == 3 { 0 } else { 1000 }).collect();
+        let bias = RawImage::new(&bias, 4, 3, 4, 1).unwrap();
+        let mut carvable = RawCarvable::new(img, EnergySource::Biased(bias), &Options::default());
+        assert_eq!(carvable.remove_seam(), Some(vec![(3, 0), (3, 1), (3, 2)]));
+    }
+
+    #[test]
+    fn restores_seams() {
+        let data: Vec<u8> = (0..30).map(|i| (i * 53 % 17) as u8).collect();
+        let img = RawImage::new(&data, 6, 5, 6, 1).unwrap();
+        let mut carvable = RawCarvable::new(img, EnergySource::Gradient, &Options::default());
+        carvable.set_width(4);
+        let narrow = carvable.result();
+        carvable.set_width(2);
+        carvable.set_width(10);
+        assert_eq!(carvable.dimensions(), (6, 5));
+        assert_eq!(carvable.result(), data);
+        carvable.set_width(4);
+        assert_eq!(carvable.result(), narrow);
+        assert_eq!(carvable.plan().seams.len(), 2);
+    }
+}
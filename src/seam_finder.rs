@@ -1,45 +1,133 @@
-use std::iter::successors;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::successors;
+use core::ops::Range;
 
 use crate::matrix::Matrix;
 use crate::pos::Pos;
+use crate::{Options, TieBreak};
 
 #[derive(Debug)]
 pub(crate) struct SeamFinder {
     size: Pos,
 
+    // Maximal horizontal distance between two consecutive pixels of a seam
+    max_step: u32,
+
+    // How to choose between seams of equal energy
+    tie_break: TieBreak,
+
+    // The column of each cell in the original image, for the tie breaks that depend on it.
+    // Ranking cells on their current column would make cached choices inconsistent
+    // with the ones made after the columns shift, letting paths cross removed seams.
+    columns: Option<Matrix<u32>>,
+
+    // The width of the original image
+    original_width: u32,
+
+    // How the dependencies and energies are stored in `contents`
+    packing: Packing,
+
     // The dependencies and energies
-    contents: Matrix<Option<SeamElem>>,
+    contents: Matrix<PackedElem>,
 
     // Vector used during invalid position clearing
     to_clear: Vec<Pos>,
 
-    // min and max x values that will have to be recomputed
+    // For each row, the min and max x values that will have to be recomputed
     dirty_bounds: DirtyBounds,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 struct SeamElem {
     predecessor_dx: i8,
     energy: u32,
 }
 
+/// A cell of `contents`: an `Option<SeamElem>` packed in 32 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PackedElem(u32);
+
+impl PackedElem {
+    /// All bits set: a cell that has not been computed
+    const EMPTY: PackedElem = PackedElem(u32::MAX);
+}
+
+/// Stores the energy in the high bits of a [PackedElem], and the offset of
+/// the predecessor plus `max_step` in the `dx_bits` low bits.
+/// There are enough low bits for their all-ones value to never be an offset,
+/// so that [PackedElem::EMPTY] is never a computed cell.
+/// Energies that do not fit in the high bits saturate.
+#[derive(Debug, Clone, Copy)]
+struct Packing {
+    dx_bits: u32,
+    max_step: u32,
+}
+
+/// The highest seam energy that is stored for the given `max_step`: higher ones saturate
+pub(crate) fn max_energy(max_step: u32) -> u32 {
+    u32::MAX >> Packing::new(max_step).dx_bits
+}
+
+impl Packing {
+    fn new(max_step: u32) -> Self {
+        let dx_bits = u32::BITS - (2 * max_step + 1).leading_zeros();
+        Packing { dx_bits, max_step }
+    }
+
+    #[inline(always)]
+    fn pack(self, elem: Option<SeamElem>) -> PackedElem {
+        match elem {
+            Some(SeamElem { predecessor_dx, energy }) => {
+                let energy = energy.min(u32::MAX >> self.dx_bits);
+                let dx = (i32::from(predecessor_dx) + self.max_step as i32) as u32;
+                PackedElem(energy << self.dx_bits | dx)
+            }
+            None => PackedElem::EMPTY,
+        }
+    }
+
+    #[inline(always)]
+    fn unpack(self, packed: PackedElem) -> Option<SeamElem> {
+        if packed == PackedElem::EMPTY {
+            return None;
+        }
+        let dx = packed.0 & ((1 << self.dx_bits) - 1);
+        Some(SeamElem {
+            predecessor_dx: (dx as i32 - self.max_step as i32) as i8,
+            energy: packed.0 >> self.dx_bits,
+        })
+    }
+}
+
+/// For each row, the min and max x values that will have to be recomputed
 #[derive(Debug)]
-struct DirtyBounds(u32, u32);
+struct DirtyBounds(Vec<(u32, u32)>);
 
 impl DirtyBounds {
-    fn clean(size: Pos) -> Self {
-        DirtyBounds(size.0, 0)
-    }
     fn dirty(size: Pos) -> Self {
-        DirtyBounds(0, size.0)
+        DirtyBounds(vec![(0, size.0); size.1 as usize])
+    }
+    fn clean(&mut self) {
+        self.0.iter_mut().for_each(|bounds| *bounds = (u32::MAX, 0));
     }
     #[inline(always)]
-    fn update(&mut self, Pos(x, _): Pos) {
-        if x < self.0 {
-            self.0 = x
+    fn update(&mut self, Pos(x, y): Pos) {
+        let bounds = &mut self.0[y as usize];
+        if x < bounds.0 {
+            bounds.0 = x
         }
-        if x >= self.1 {
-            self.1 = x + 1
+        if x >= bounds.1 {
+            bounds.1 = x + 1
+        }
+    }
+    /// Keeps covering the dirty positions after a seam is inserted before them
+    #[inline(always)]
+    fn shift_right(&mut self, size: Pos) {
+        for bounds in &mut self.0 {
+            if bounds.0 < bounds.1 {
+                bounds.1 = (bounds.1 + 1).min(size.0)
+            }
         }
     }
 }
@@ -65,12 +153,23 @@ impl SeamElem {
 }
 
 impl SeamFinder {
-    pub fn new(size: Pos) -> Self {
-        let contents: Matrix<Option<SeamElem>> = Matrix::from_fn(size, |_, _| None);
+    pub fn new(size: Pos, options: &Options) -> Self {
+        let contents = Matrix::from_fn(size, |_, _| PackedElem::EMPTY);
         let to_clear = Vec::with_capacity(size.1 as usize);
         let dirty_bounds = DirtyBounds::dirty(size);
+        let columns = match options.tie_break {
+            TieBreak::Leftmost | TieBreak::Rightmost => None,
+            TieBreak::Center | TieBreak::Random(_) => {
+                Some(Matrix::from_fn(size, |x, _| x as u32))
+            }
+        };
         SeamFinder {
             size,
+            max_step: u32::from(options.max_step),
+            tie_break: options.tie_break,
+            columns,
+            original_width: size.0,
+            packing: Packing::new(u32::from(options.max_step)),
             contents,
             to_clear,
             dirty_bounds,
@@ -78,65 +177,350 @@ impl SeamFinder {
     }
 
     pub fn extract_seam<F: FnMut(Pos) -> u32>(&mut self, energy: F) -> Vec<Pos> {
-        self.fill(energy);
+        let seam = self.find_seam::<_, false>(energy);
+        self.remove_cells(&seam);
+        seam
+    }
+
+    /// Extracts up to `count` seams that do not share any pixel, from a single
+    /// computation of the seam energies. This is faster, but only the first seam
+    /// is guaranteed to be the lowest-energy one: the following ones leave
+    /// their lowest-energy path where it meets a seam that was already found.
+    ///
+    /// Each seam is given in the coordinates of the image after the previous ones are removed.
+    pub fn extract_seams<F: FnMut(Pos) -> u32>(&mut self, energy: F, count: u32) -> Vec<Vec<Pos>> {
+        if count <= 1 || self.size.1 == 0 {
+            return vec![self.extract_seam(energy)];
+        }
+        self.fill::<F, false>(energy);
+        let Pos(width, height) = self.size;
+        let bottom = height - 1;
+        let mut starts: Vec<Pos> = (0..width).map(|x| Pos(x, bottom)).collect();
+        starts.sort_by_cached_key(|&p| {
+            let energy = self.cell::<false>(p).expect("should have been filled").energy;
+            (energy, self.rank::<false>(p))
+        });
+        // Backtrack from the best bottom pixels. When the best path goes through a pixel
+        // of a seam that was already found, continue with the best free predecessor instead.
+        let mut taken = vec![false; width as usize * height as usize];
+        let index = |Pos(x, y): Pos| (y * width + x) as usize;
+        let mut found: Vec<Vec<Pos>> = Vec::with_capacity(count as usize);
+        for start in starts {
+            if found.len() == count as usize {
+                break;
+            }
+            if taken[index(start)] {
+                continue;
+            }
+            let seam: Vec<Pos> = successors(Some(start), |&pos| {
+                if pos.1 == 0 {
+                    return None;
+                }
+                let best = self.cell::<false>(pos).expect("should be filled").predecessor(pos);
+                if !taken[index(best)] {
+                    return Some(best);
+                }
+                pos.predecessors(self.size, self.max_step)
+                    .filter(|&p| !taken[index(p)])
+                    .min_by_key(|&p| {
+                        let energy = self.cell::<false>(p).expect("should be filled").energy;
+                        (energy, self.rank::<false>(p))
+                    })
+            })
+            .collect();
+            if seam.len() == height as usize {
+                seam.iter().for_each(|&p| taken[index(p)] = true);
+                found.push(seam);
+            }
+        }
+        let mut seams = found.clone();
+        for (i, seam) in seams.iter_mut().enumerate() {
+            for (row, pos) in seam.iter_mut().enumerate() {
+                let removed_before = found[..i].iter().filter(|s| s[row].0 < pos.0).count();
+                pos.0 -= removed_before as u32;
+            }
+            seam.iter().for_each(|&pos| self.clear::<false>(pos));
+            self.clear_crossing::<false>(seam);
+            self.size.0 -= 1;
+            self.remove_cells(seam);
+        }
+        seams
+    }
+
+    /// Finds the lowest-energy seam that stays inside the given range of columns on each row,
+    /// and removes it.
+    ///
+    /// Only the positions inside the ranges are computed, and the cached values of the
+    /// other positions are ignored, so this should not be mixed with
+    /// [extract_seam](SeamFinder::extract_seam) on the same finder.
+    /// Returns `None` if no seam fits in the ranges.
+    pub fn extract_seam_in<F: FnMut(Pos) -> u32>(
+        &mut self,
+        columns: &[Range<u32>],
+        mut energy: F,
+    ) -> Option<Vec<Pos>> {
+        debug_assert_eq!(columns.len(), self.size.1 as usize);
+        for (y, range) in (0..).zip(columns) {
+            for x in range.clone() {
+                let pos = Pos(x, y);
+                let delta_e = energy(pos);
+                let mut best: Option<(u32, Pos)> = None;
+                if y > 0 {
+                    let previous = &columns[y as usize - 1];
+                    for predecessor in pos.predecessors(self.size, self.max_step) {
+                        if !previous.contains(&predecessor.0) {
+                            continue;
+                        }
+                        if let Some(e) = self.cell::<false>(predecessor) {
+                            let energy = e.energy.saturating_add(delta_e);
+                            let is_better = match best {
+                                None => true,
+                                Some((best_energy, best_predecessor)) => {
+                                    energy < best_energy
+                                        || (energy == best_energy
+                                            && self.prefers::<false>(predecessor, best_predecessor))
+                                }
+                            };
+                            if is_better {
+                                best = Some((energy, predecessor));
+                            }
+                        }
+                    }
+                }
+                let elem = match best {
+                    Some((energy, predecessor)) => {
+                        let mut elem = SeamElem::new(energy);
+                        elem.set_dx(pos, predecessor);
+                        Some(elem)
+                    }
+                    None if y == 0 => Some(SeamElem::new(delta_e)),
+                    None => None, // Unreachable from the top row
+                };
+                self.set_cell::<false>(pos, elem);
+            }
+        }
+        let bottom_y = self.size.1.checked_sub(1)?;
+        let init = columns[bottom_y as usize]
+            .clone()
+            .map(|x| Pos(x, bottom_y))
+            .filter_map(|p| self.cell::<false>(p).map(|e| (e.energy, p)))
+            .min_by_key(|&(energy, p)| (energy, self.rank::<false>(p)))?;
+        let seam: Vec<Pos> = successors(Some(init.1), |&pos| {
+            if pos.1 == 0 {
+                return None;
+            }
+            Some(self.cell::<false>(pos).expect("should be filled").predecessor(pos))
+        })
+        .collect();
+        self.size.0 -= 1;
+        self.remove_cells(&seam);
+        Some(seam)
+    }
+
+    /// Like [extract_seam](SeamFinder::extract_seam), but defers moving the cached values
+    /// until [compact](SeamFinder::compact) is called, which is cheaper when
+    /// extracting many seams in a row.
+    pub fn extract_seam_deferred<F: FnMut(Pos) -> u32>(&mut self, energy: F) -> Vec<Pos> {
+        let seam = if self.contents.has_pending() {
+            self.find_seam::<_, true>(energy)
+        } else {
+            self.find_seam::<_, false>(energy)
+        };
+        self.contents.defer_seam_removal(&seam);
+        if let Some(columns) = &mut self.columns {
+            columns.defer_seam_removal(&seam);
+        }
+        seam
+    }
+
+    /// Applies the removals of the seams extracted with
+    /// [extract_seam_deferred](SeamFinder::extract_seam_deferred)
+    pub fn compact(&mut self) {
+        self.contents.compact();
+        if let Some(columns) = &mut self.columns {
+            columns.compact();
+        }
+    }
+
+    /// Removes the cells of a seam, after they were cleared
+    fn remove_cells(&mut self, seam: &[Pos]) {
+        self.contents.remove_seam(seam);
+        if let Some(columns) = &mut self.columns {
+            columns.remove_seam(seam);
+        }
+    }
+
+    /// Finds the next seam. `PENDING` tells whether the matrix has deferred removals:
+    /// taking them into account makes every access slower, so it is only done when needed.
+    fn find_seam<F: FnMut(Pos) -> u32, const PENDING: bool>(&mut self, energy: F) -> Vec<Pos> {
+        self.fill::<F, PENDING>(energy);
         let mut seam = Vec::with_capacity(self.size.1 as usize);
         // Find the bottom pixel with the lowest energy
         let bottom_y: Option<u32> = self.size.1.checked_sub(1);
         let init = (0..self.size.0)
             .flat_map(|x| bottom_y.map(|y| Pos(x, y)))
-            .min_by_key(|&p|
-                self.contents[p].as_ref().expect("should have been filled").energy);
+            .min_by_key(|&p| {
+                let energy = self.cell::<PENDING>(p).expect("should have been filled").energy;
+                (energy, self.rank::<PENDING>(p))
+            });
         seam.extend(successors(init, |&pos| {
             let next = if pos.1 == 0 {
                 None
             } else {
-                Some(self.contents[pos]
-                    .as_ref()
+                Some(self.cell::<PENDING>(pos)
                     .expect("should be filled")
                     .predecessor(pos))
             };
-            self.clear(pos);
+            self.clear::<PENDING>(pos);
             next
         }));
+        self.clear_crossing::<PENDING>(&seam);
         self.size.0 -= 1;
-        self.contents.remove_seam(&seam);
         seam
     }
 
-    fn fill<F: FnMut(Pos) -> u32>(&mut self, mut energy: F) {
-        let start = Pos(self.dirty_bounds.0, 0);
-        let end = Pos(self.dirty_bounds.1, self.size.1);
-        for pos in Pos::iter_in_rect(start, end) {
-            if self.contents[pos].is_some() {
+    fn fill<F: FnMut(Pos) -> u32, const PENDING: bool>(&mut self, mut energy: F) {
+        let bounds = core::mem::take(&mut self.dirty_bounds.0);
+        let width = self.size.0;
+        let dirty = bounds.iter().zip(0..).flat_map(|(&(start, end), y)| {
+            // Positions cleared before the last seam was removed can be out of the image
+            (start..end.min(width)).map(move |x| Pos(x, y))
+        });
+        for pos in dirty {
+            if self.cell::<PENDING>(pos).is_some() {
                 continue;
             }
             let delta_e = energy(pos);
-            let mut best_elem = SeamElem::new(std::u32::MAX);
-            for predecessor in pos.predecessors(self.size) {
-                if let Some(e) = &self.contents[predecessor] {
-                    let energy = e.energy + delta_e;
-                    if energy < best_elem.energy {
-                        best_elem.energy = energy;
-                        best_elem.set_dx(pos, predecessor);
+            let mut best: Option<(u32, Pos)> = None;
+            for predecessor in pos.predecessors(self.size, self.max_step) {
+                if let Some(e) = self.cell::<PENDING>(predecessor) {
+                    let energy = e.energy.saturating_add(delta_e);
+                    let is_better = match best {
+                        None => true,
+                        Some((best_energy, best_predecessor)) => {
+                            energy < best_energy
+                                || (energy == best_energy
+                                    && self.prefers::<PENDING>(predecessor, best_predecessor))
+                        }
+                    };
+                    if is_better {
+                        best = Some((energy, predecessor));
                     }
                 }
             }
-            if best_elem.energy == std::u32::MAX { // We are on the top row
-                best_elem.energy = delta_e;
+            let best_elem = match best {
+                Some((energy, predecessor)) => {
+                    let mut elem = SeamElem::new(energy);
+                    elem.set_dx(pos, predecessor);
+                    elem
+                }
+                None => SeamElem::new(delta_e), // We are on the top row
+            };
+            self.set_cell::<PENDING>(pos, Some(best_elem));
+        }
+        self.dirty_bounds.0 = bounds;
+        self.dirty_bounds.clean();
+    }
+
+    /// Puts back a seam returned by [extract_seam](SeamFinder::extract_seam).
+    /// Seams must be restored in the reverse order of their extraction.
+    pub fn restore_seam(&mut self, seam: &[Pos]) {
+        self.size.0 += 1;
+        self.contents.restore_seam(seam);
+        if let Some(columns) = &mut self.columns {
+            columns.restore_seam(seam);
+        }
+        self.dirty_bounds.shift_right(self.size);
+        // Pixels around the seam have new neighbours, and the predecessors
+        // of the pixels that are on one side of the seam while their predecessor
+        // is on the other side are now off by one.
+        let margin = 2 * self.max_step + 1;
+        for &Pos(x, y) in seam {
+            let start = x.saturating_sub(margin);
+            let end = (x + margin + 1).min(self.size.0);
+            (start..end).for_each(|x| self.clear::<false>(Pos(x, y)));
+        }
+    }
+
+    /// Removes again the last seam put back with [restore_seam](SeamFinder::restore_seam),
+    /// given in the same form, without searching for it
+    pub fn remove_seam(&mut self, seam: &[Pos]) {
+        seam.iter().for_each(|&pos| self.clear::<false>(pos));
+        self.clear_crossing::<false>(seam);
+        self.size.0 -= 1;
+        self.remove_cells(seam);
+    }
+
+    #[inline(always)]
+    fn cell<const PENDING: bool>(&self, pos: Pos) -> Option<SeamElem> {
+        let packed = if PENDING {
+            *self.contents.get_pending(pos)
+        } else {
+            self.contents[pos]
+        };
+        self.packing.unpack(packed)
+    }
+
+    #[inline(always)]
+    fn set_cell<const PENDING: bool>(&mut self, pos: Pos, elem: Option<SeamElem>) {
+        let packed = self.packing.pack(elem);
+        if PENDING {
+            *self.contents.get_pending_mut(pos) = packed
+        } else {
+            self.contents[pos] = packed
+        }
+    }
+
+    /// Whether `a` should be chosen over `b` when both have the same energy
+    #[inline]
+    fn prefers<const PENDING: bool>(&self, a: Pos, b: Pos) -> bool {
+        match self.tie_break {
+            TieBreak::Leftmost => false, // Predecessors are visited from left to right
+            _ => self.rank::<PENDING>(a) < self.rank::<PENDING>(b),
+        }
+    }
+
+    /// The tie break key of a position, computed on its position in the original image,
+    /// so that it does not change when seams are removed
+    #[inline]
+    fn rank<const PENDING: bool>(&self, pos: Pos) -> u64 {
+        let original = match &self.columns {
+            Some(columns) if PENDING => Pos(*columns.get_pending(pos), pos.1),
+            Some(columns) => Pos(columns[pos], pos.1),
+            None => pos,
+        };
+        self.tie_break.rank(original, self.original_width)
+    }
+
+    /// Invalidates the positions whose predecessor is on the other side of a seam
+    /// that is about to be removed, given from the bottom to the top.
+    /// Their offset would be wrong after the removal. Optimal paths do not cross,
+    /// but a cached choice can become stale when a neighbour of its predecessor
+    /// is recomputed, and a seam found later can then go between them.
+    fn clear_crossing<const PENDING: bool>(&mut self, seam: &[Pos]) {
+        let margin = 2 * self.max_step + 1;
+        for pair in seam.windows(2) {
+            let (Pos(x, y), Pos(above, _)) = (pair[0], pair[1]);
+            let start = x.saturating_sub(margin);
+            let end = (x + margin + 1).min(self.size.0);
+            for pos in (start..end).map(|x| Pos(x, y)) {
+                if let Some(e) = self.cell::<PENDING>(pos) {
+                    if (pos.0 > x) != (e.predecessor(pos).0 > above) {
+                        self.clear::<PENDING>(pos);
+                    }
+                }
             }
-            self.contents[pos] = Some(best_elem);
         }
-        self.dirty_bounds = DirtyBounds::clean(self.size);
     }
 
     /// Recursively invalidates all cached information about a position
-    fn clear(&mut self, p: Pos) {
+    fn clear<const PENDING: bool>(&mut self, p: Pos) {
         self.to_clear.push(p);
         while let Some(pos) = self.to_clear.pop() {
-            self.contents[pos] = None;
+            self.set_cell::<PENDING>(pos, None);
             self.dirty_bounds.update(pos);
-            for s in pos.successors(self.size) {
-                if let Some(e) = &self.contents[s] {
+            for s in pos.successors(self.size, self.max_step) {
+                if let Some(e) = self.cell::<PENDING>(s) {
                     if e.predecessor(s) == pos {
                         self.to_clear.push(s)
                     }
@@ -149,11 +533,31 @@ impl SeamFinder {
 #[cfg(test)]
 mod tests {
     use crate::pos::Pos;
-    use crate::seam_finder::SeamFinder;
+    use crate::seam_finder::{Packing, PackedElem, SeamElem, SeamFinder};
+    use crate::{Options, TieBreak};
+
+    #[test]
+    fn packs_cells() {
+        for &max_step in &[0, 1, 2, 127] {
+            let packing = Packing::new(max_step);
+            let step = max_step as i8;
+            for &(predecessor_dx, energy) in &[(-step, 0), (0, 12345), (step, 1 << 20)] {
+                let elem = SeamElem { predecessor_dx, energy };
+                let packed = packing.pack(Some(elem));
+                assert_ne!(packed, PackedElem::EMPTY);
+                let unpacked = packing.unpack(packed).unwrap();
+                assert_eq!((unpacked.predecessor_dx, unpacked.energy), (predecessor_dx, energy));
+            }
+            assert!(packing.unpack(packing.pack(None)).is_none());
+            let huge = packing.unpack(packing.pack(Some(SeamElem::new(u32::MAX)))).unwrap();
+            assert_eq!(huge.energy, u32::MAX >> packing.dx_bits);
+        }
+        assert_eq!(Packing::new(1).dx_bits, 2);
+    }
 
     #[test]
     fn extracts_correct_seam() {
-        let mut finder = SeamFinder::new(Pos(3, 2));
+        let mut finder = SeamFinder::new(Pos(3, 2), &Options::default());
         let energy_fn = |Pos(x, _y)| x;
         // energy matrix:
         // 0  1  2
@@ -166,7 +570,7 @@ mod tests {
     #[test]
     fn larger_image_1024x256() {
         let (w, h) = (1024, 256);
-        let mut finder = SeamFinder::new(Pos(w, h));
+        let mut finder = SeamFinder::new(Pos(w, h), &Options::default());
         let energy_fn = |Pos(x, _y)| x;
         let s1 = finder.extract_seam(energy_fn);
         let expected: Vec<_> = (0..h).rev().map(|y| Pos(0, y)).collect();
@@ -175,9 +579,75 @@ mod tests {
 
     #[test]
     fn fills() {
-        let mut finder = SeamFinder::new(Pos(10, 10));
-        finder.fill(|_| 42);
+        let mut finder = SeamFinder::new(Pos(10, 10), &Options::default());
+        finder.fill::<_, false>(|_| 42);
         Pos::iter_in_rect(Pos(0, 0), finder.size)
-            .for_each(|p| assert!(finder.contents[p].is_some()))
+            .for_each(|p| assert!(finder.cell::<false>(p).is_some()))
+    }
+
+    #[test]
+    fn larger_steps() {
+        let mut finder = SeamFinder::new(Pos(4, 3), &Options::default().max_step(2));
+        // The cheapest path goes from x=3 to x=1 and then x=3
+        let energy_fn = |Pos(x, y)| if x == 3 - 2 * (y % 2) { 0 } else { 9 };
+        let s1 = finder.extract_seam(energy_fn);
+        assert_eq!(s1, vec![Pos(3, 2), Pos(1, 1), Pos(3, 0)]);
+    }
+
+    #[test]
+    fn straight_seams() {
+        let mut finder = SeamFinder::new(Pos(3, 3), &Options::default().max_step(0));
+        let energy_fn = |Pos(x, y)| if x == y { 0 } else { 1 };
+        let s1 = finder.extract_seam(energy_fn);
+        assert_eq!(s1, vec![Pos(0, 2), Pos(0, 1), Pos(0, 0)]);
+    }
+
+    #[test]
+    fn several_seams_per_pass() {
+        let mut finder = SeamFinder::new(Pos(6, 3), &Options::default());
+        // Columns 1 and 4 are free
+        let energy_fn = |Pos(x, _y)| if x == 1 || x == 4 { 0 } else { 5 };
+        let seams = finder.extract_seams(energy_fn, 2);
+        let column = |x| vec![Pos(x, 2), Pos(x, 1), Pos(x, 0)];
+        // The second seam is given after the removal of the first one
+        assert_eq!(seams, vec![column(1), column(3)]);
+        assert_eq!(finder.size, Pos(4, 3));
+    }
+
+    #[test]
+    fn seam_in_columns() {
+        let mut finder = SeamFinder::new(Pos(6, 3), &Options::default());
+        let energy_fn = |Pos(x, _y)| x;
+        let columns = [3..5, 2..4, 4..6];
+        let seam = finder.extract_seam_in(&columns, energy_fn);
+        assert_eq!(seam, Some(vec![Pos(4, 2), Pos(3, 1), Pos(3, 0)]));
+        let disconnected = [0..1, 3..4, 3..4];
+        assert_eq!(finder.extract_seam_in(&disconnected, energy_fn), None);
+    }
+
+    #[test]
+    fn tie_break_policies() {
+        let flat = |_| 1;
+        let seam_x = |tie_break| {
+            let mut finder = SeamFinder::new(Pos(5, 3), &Options::default().tie_break(tie_break));
+            let seam = finder.extract_seam(flat);
+            seam.iter().map(|p| p.0).collect::<Vec<_>>()
+        };
+        assert_eq!(seam_x(TieBreak::Leftmost), vec![0, 0, 0]);
+        assert_eq!(seam_x(TieBreak::Rightmost), vec![4, 4, 4]);
+        assert_eq!(seam_x(TieBreak::Center), vec![2, 2, 2]);
+        assert_eq!(seam_x(TieBreak::Random(42)), seam_x(TieBreak::Random(42)));
+    }
+
+    #[test]
+    fn restores_seams() {
+        let mut finder = SeamFinder::new(Pos(6, 4), &Options::default());
+        let energy_fn = |Pos(x, y)| (x * 5 + y * 3) % 7;
+        let s1 = finder.extract_seam(energy_fn);
+        let s2 = finder.extract_seam(energy_fn);
+        finder.restore_seam(&s2);
+        finder.restore_seam(&s1);
+        assert_eq!(finder.size, Pos(6, 4));
+        assert_eq!(finder.extract_seam(energy_fn), s1);
     }
 }
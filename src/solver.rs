@@ -0,0 +1,176 @@
+#[cfg(feature = "image")]
+use image::GenericImageView;
+
+#[cfg(feature = "image")]
+use crate::energy::{energy_fn, square_diff_px};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::graph_cut::GraphCut;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+use crate::{Options, Solver};
+
+/// An algorithm that finds the seams to remove from an image
+pub(crate) trait SeamSolver {
+    /// Finds the lowest cost vertical seam,
+    /// and forgets about it so that the next call returns the following seam.
+    ///
+    /// The seam is returned from the bottom row to the top row.
+    fn extract_seam<E: EnergyMap>(&mut self, energy: &mut E) -> Vec<Pos>;
+
+    /// Finds up to `count` seams at once, possibly less accurately than one at a time.
+    /// Each seam is given in the coordinates of the image after the previous ones are removed.
+    /// At least one seam is always returned.
+    fn extract_seams<E: EnergyMap>(&mut self, energy: &mut E, _count: u32) -> Vec<Vec<Pos>> {
+        vec![self.extract_seam(energy)]
+    }
+
+    /// Like [extract_seam](SeamSolver::extract_seam), but the solver may postpone
+    /// updating its internal state until [compact](SeamSolver::compact) is called
+    fn extract_seam_deferred<E: EnergyMap>(&mut self, energy: &mut E) -> Vec<Pos> {
+        self.extract_seam(energy)
+    }
+
+    /// Applies the postponed updates of [extract_seam_deferred](SeamSolver::extract_seam_deferred)
+    fn compact(&mut self) {}
+
+    /// Puts back the last extracted seam, as returned by [extract_seam](SeamSolver::extract_seam)
+    fn restore_seam(&mut self, seam: &[Pos]);
+
+    /// Removes again the last seam put back with [restore_seam](SeamSolver::restore_seam),
+    /// without searching for it
+    fn remove_seam(&mut self, seam: &[Pos]);
+}
+
+/// The information about an image that solvers use to compute the cost of a seam
+pub(crate) trait EnergyMap {
+    /// Energy of a single pixel
+    fn energy(&mut self, pos: Pos) -> u32;
+    /// Difference between two pixels,
+    /// used to compute the energy of the edges that appear when removing a seam
+    fn distance(&mut self, a: Pos, b: Pos) -> u32;
+}
+
+/// The cost of a seam, given in the coordinates of the current image:
+/// the sum of the current energies of the seam's pixels. It can differ from the
+/// energy cached by the dynamic programming solver, which does not recompute
+/// every energy after each removal, and saturates.
+pub(crate) fn seam_cost<E: EnergyMap>(energy: &mut E, seam: &[Pos]) -> u64 {
+    seam.iter().map(|&pos| u64::from(energy.energy(pos))).sum()
+}
+
+/// The energy of the pixels of an image
+#[cfg(feature = "image")]
+pub(crate) struct ImageEnergy<'a, IMG>(pub &'a IMG);
+
+#[cfg(feature = "image")]
+impl<'a, IMG: GenericImageView> EnergyMap for ImageEnergy<'a, IMG> {
+    #[inline(always)]
+    fn energy(&mut self, pos: Pos) -> u32 {
+        energy_fn(self.0, pos)
+    }
+
+    #[inline(always)]
+    fn distance(&mut self, a: Pos, b: Pos) -> u32 {
+        square_diff_px(self.0.get_pixel(a.0, a.1), self.0.get_pixel(b.0, b.1))
+    }
+}
+
+/// The solver selected in the options
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)] // There is a single solver per image
+pub(crate) enum AnySolver {
+    DynamicProgramming(SeamFinder),
+    GraphCut(GraphCut),
+}
+
+impl AnySolver {
+    pub fn new(size: Pos, options: &Options) -> Self {
+        match options.solver {
+            Solver::DynamicProgramming => {
+                AnySolver::DynamicProgramming(SeamFinder::new(size, options))
+            }
+            Solver::GraphCut { forward_energy } => {
+                AnySolver::GraphCut(GraphCut::new(size, options, forward_energy))
+            }
+        }
+    }
+}
+
+impl SeamSolver for AnySolver {
+    #[inline]
+    fn extract_seam<E: EnergyMap>(&mut self, energy: &mut E) -> Vec<Pos> {
+        match self {
+            AnySolver::DynamicProgramming(s) => s.extract_seam(|p| energy.energy(p)),
+            AnySolver::GraphCut(s) => s.extract_seam(energy),
+        }
+    }
+
+    fn extract_seams<E: EnergyMap>(&mut self, energy: &mut E, count: u32) -> Vec<Vec<Pos>> {
+        match self {
+            AnySolver::DynamicProgramming(s) => s.extract_seams(|p| energy.energy(p), count),
+            AnySolver::GraphCut(s) => s.extract_seams(energy, count),
+        }
+    }
+
+    #[inline]
+    fn extract_seam_deferred<E: EnergyMap>(&mut self, energy: &mut E) -> Vec<Pos> {
+        match self {
+            AnySolver::DynamicProgramming(s) => s.extract_seam_deferred(|p| energy.energy(p)),
+            AnySolver::GraphCut(s) => s.extract_seam(energy),
+        }
+    }
+
+    fn compact(&mut self) {
+        if let AnySolver::DynamicProgramming(s) = self {
+            s.compact()
+        }
+    }
+
+    #[inline]
+    fn restore_seam(&mut self, seam: &[Pos]) {
+        match self {
+            AnySolver::DynamicProgramming(s) => s.restore_seam(seam),
+            AnySolver::GraphCut(s) => s.restore_seam(seam),
+        }
+    }
+
+    #[inline]
+    fn remove_seam(&mut self, seam: &[Pos]) {
+        match self {
+            AnySolver::DynamicProgramming(s) => SeamSolver::remove_seam(s, seam),
+            AnySolver::GraphCut(s) => s.remove_seam(seam),
+        }
+    }
+}
+
+impl SeamSolver for SeamFinder {
+    #[inline]
+    fn extract_seam<E: EnergyMap>(&mut self, energy: &mut E) -> Vec<Pos> {
+        SeamFinder::extract_seam(self, |p| energy.energy(p))
+    }
+
+    fn extract_seams<E: EnergyMap>(&mut self, energy: &mut E, count: u32) -> Vec<Vec<Pos>> {
+        SeamFinder::extract_seams(self, |p| energy.energy(p), count)
+    }
+
+    #[inline]
+    fn extract_seam_deferred<E: EnergyMap>(&mut self, energy: &mut E) -> Vec<Pos> {
+        SeamFinder::extract_seam_deferred(self, |p| energy.energy(p))
+    }
+
+    fn compact(&mut self) {
+        SeamFinder::compact(self)
+    }
+
+    #[inline]
+    fn restore_seam(&mut self, seam: &[Pos]) {
+        SeamFinder::restore_seam(self, seam)
+    }
+
+    #[inline]
+    fn remove_seam(&mut self, seam: &[Pos]) {
+        SeamFinder::remove_seam(self, seam)
+    }
+}
@@ -0,0 +1,166 @@
+use std::ops::Range;
+
+use image::{GenericImageView, Pixel};
+
+use crate::energy::energy_fn;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+use crate::Options;
+
+/// Reduces the width of an image given as an iterator over its rows,
+/// keeping only a strip of `strip_height` rows in memory at a time.
+///
+/// This is meant for scans and panoramas that are too large to be loaded at once.
+/// Each strip is carved separately, and the `n`-th seam of a strip starts
+/// where the `n`-th seam of the previous one ended, so that seams stay connected
+/// across strips. Seams are still only optimal within each strip:
+/// taller strips give better results, and use more memory.
+///
+/// Only the `max_step` and `tie_break` [Options] are taken into account.
+/// Apart from the rows of the strip, the search uses four bytes per pixel of the strip,
+/// or eight with the [Center](crate::TieBreak::Center) and [Random](crate::TieBreak::Random)
+/// tie breaks.
+///
+/// All the rows must have the same length, or the iterator panics.
+/// Rows can be read from any source,
+/// for instance a raw file:
+///
+/// ```
+/// use std::io::Read;
+/// use image::Luma;
+/// let (width, height) = (40, 30);
+/// let raw: Vec<u8> = (0..width * height).map(|i| (i * 7 % 251) as u8).collect();
+/// let mut reader = std::io::Cursor::new(raw);
+/// let rows = std::iter::from_fn(|| {
+///     let mut row = vec![0; width];
+///     reader.read_exact(&mut row).ok()?;
+///     Some(row.into_iter().map(|v| Luma([v])).collect())
+/// });
+/// let options = seamcarving::Options::default();
+/// let carved: Vec<Vec<Luma<u8>>> = seamcarving::carve_rows(rows, 30, 8, &options).collect();
+/// assert_eq!(carved.len(), height);
+/// assert!(carved.iter().all(|row| row.len() == 30));
+/// ```
+pub fn carve_rows<P, I>(
+    rows: I,
+    width: u32,
+    strip_height: u32,
+    options: &Options,
+) -> CarvedRows<P, I::IntoIter>
+where
+    P: Pixel + 'static,
+    I: IntoIterator<Item = Vec<P>>,
+{
+    CarvedRows {
+        rows: rows.into_iter(),
+        width,
+        strip_height: strip_height.max(1),
+        options: options.clone(),
+        row_width: None,
+        seam_ends: None,
+        carved: Vec::new().into_iter(),
+        columns: Vec::new(),
+    }
+}
+
+/// Iterator over the carved rows of an image, returned by [carve_rows]
+pub struct CarvedRows<P, I> {
+    rows: I,
+    width: u32,
+    strip_height: u32,
+    options: Options,
+    // The length of the first row, that all the others must have
+    row_width: Option<usize>,
+    // The column of the last pixel of each seam of the previous strip
+    seam_ends: Option<Vec<u32>>,
+    // The rows of the last carved strip that were not returned yet
+    carved: std::vec::IntoIter<Vec<P>>,
+    // For each row of the strip, the columns that the current seam can go through
+    columns: Vec<Range<u32>>,
+}
+
+impl<P, I> Iterator for CarvedRows<P, I>
+where
+    P: Pixel + 'static,
+    I: Iterator<Item = Vec<P>>,
+{
+    type Item = Vec<P>;
+
+    fn next(&mut self) -> Option<Vec<P>> {
+        if let Some(row) = self.carved.next() {
+            return Some(row);
+        }
+        let mut strip: Vec<Vec<P>> = self
+            .rows
+            .by_ref()
+            .take(self.strip_height as usize)
+            .collect();
+        let original_width = *self.row_width.get_or_insert(strip.first()?.len());
+        assert!(
+            strip.iter().all(|row| row.len() == original_width),
+            "All the rows must have the same length as the first one, {}",
+            original_width
+        );
+        let count = original_width.saturating_sub(self.width as usize);
+        let size = Pos(original_width as u32, strip.len() as u32);
+        let mut finder = SeamFinder::new(size, &self.options);
+        let max_step = u32::from(self.options.max_step);
+        let mut ends = Vec::with_capacity(count);
+        for n in 0..count {
+            let current_width = (original_width - n) as u32;
+            let energy = |pos| energy_fn(&Strip(&strip), pos);
+            let seam = match &self.seam_ends {
+                Some(previous) => {
+                    // The seam starts next to the end of the previous one,
+                    // so only the pixels it can reach from there are computed
+                    let x = previous[n];
+                    self.columns.clear();
+                    self.columns.extend((1..=size.1).map(|rows| {
+                        let spread = max_step.saturating_mul(rows);
+                        x.saturating_sub(spread)..(x + 1).saturating_add(spread).min(current_width)
+                    }));
+                    finder
+                        .extract_seam_in(&self.columns, energy)
+                        .expect("a seam can reach every row")
+                }
+                None => finder.extract_seam(energy),
+            };
+            for (row, &Pos(x, _)) in strip.iter_mut().zip(seam.iter().rev()) {
+                row.remove(x as usize);
+            }
+            ends.push(seam[0].0);
+        }
+        self.seam_ends = Some(ends);
+        self.carved = strip.into_iter();
+        self.carved.next()
+    }
+}
+
+/// The rows of a strip, as an image
+struct Strip<'a, P>(&'a [Vec<P>]);
+
+impl<'a, P: Pixel + 'static> GenericImageView for Strip<'a, P> {
+    type Pixel = P;
+    type InnerImageView = Self;
+
+    #[inline(always)]
+    fn dimensions(&self) -> (u32, u32) {
+        let width = self.0.first().map_or(0, Vec::len);
+        (width as u32, self.0.len() as u32)
+    }
+
+    #[inline(always)]
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        let (w, h) = self.dimensions();
+        (0, 0, w, h)
+    }
+
+    #[inline(always)]
+    fn get_pixel(&self, x: u32, y: u32) -> P {
+        self.0[y as usize][x as usize]
+    }
+
+    fn inner(&self) -> &Self {
+        self
+    }
+}
@@ -0,0 +1,173 @@
+use image::GenericImageView;
+
+use crate::energy::energy_fn;
+use crate::graph_cut::GraphCut;
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+use crate::solver::ImageEnergy;
+use crate::{image_view_to_buffer, max_pos, BufferOf, Carved, Options, Rotated};
+
+/// Resizes all the frames of a video to a lower width and height,
+/// keeping the seams of consecutive frames close to each other.
+///
+/// Carving every frame independently makes the seams jump from one frame
+/// to the next, which is visible as jitter. Here, the energy of a pixel
+/// is increased by `temporal_weight` units for every pixel that separates it
+/// from the closest pixel of its row removed from the previous frame,
+/// in the original frames.
+/// A weight of `0` is equivalent to resizing each frame separately.
+///
+/// All the frames must have the same dimensions.
+///
+/// ```no_run
+/// # use image::GrayImage;
+/// let frames = vec![GrayImage::new(100, 100); 10];
+/// let resized = seamcarving::resize_video(&frames, 50, 100, 100);
+/// assert_eq!(resized.len(), 10);
+/// ```
+pub fn resize_video<IMG: GenericImageView>(
+    frames: &[IMG],
+    width: u32,
+    height: u32,
+    temporal_weight: u32,
+) -> Vec<BufferOf<IMG>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(to_remove_x, to_remove_y) = video_size(frames) - Pos(width, height);
+    let carved_x = carve_frames(frames, to_remove_x, temporal_weight);
+    let rotated: Vec<_> = carved_x.iter().map(Rotated).collect();
+    let carved_y = carve_frames(&rotated, to_remove_y, temporal_weight);
+    carved_y
+        .iter()
+        .map(|frame| image_view_to_buffer(&Rotated(frame)))
+        .collect()
+}
+
+/// Resizes all the frames of a video to a lower width and height,
+/// by removing seam surfaces.
+///
+/// A seam surface is made of one seam per frame, such that the seams
+/// of two consecutive frames are never more than one pixel apart on a given row.
+/// The surface with the lowest total energy is found with a minimum graph cut,
+/// which is much slower than [resize_video], but finds the optimal surface.
+///
+/// With `forward_energy`, the cost of a surface is the energy of the edges
+/// its removal creates between pixels that were not adjacent,
+/// instead of the energy of the removed pixels.
+///
+/// All the frames must have the same dimensions.
+pub fn resize_video_surfaces<IMG: GenericImageView>(
+    frames: &[IMG],
+    width: u32,
+    height: u32,
+    forward_energy: bool,
+) -> Vec<BufferOf<IMG>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let Pos(to_remove_x, to_remove_y) = video_size(frames) - Pos(width, height);
+    let carved_x = carve_surfaces(frames, to_remove_x, forward_energy);
+    let rotated: Vec<_> = carved_x.iter().map(Rotated).collect();
+    let carved_y = carve_surfaces(&rotated, to_remove_y, forward_energy);
+    carved_y
+        .iter()
+        .map(|frame| image_view_to_buffer(&Rotated(frame)))
+        .collect()
+}
+
+fn video_size<IMG: GenericImageView>(frames: &[IMG]) -> Pos {
+    let size = frames.first().map_or(Pos(0, 0), max_pos);
+    assert!(
+        frames.iter().all(|f| max_pos(f) == size),
+        "all the frames of a video must have the same dimensions"
+    );
+    size
+}
+
+fn carve_surfaces<IMG: GenericImageView>(
+    frames: &[IMG],
+    pixel_count: u32,
+    forward_energy: bool,
+) -> Vec<Carved<'_, IMG>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut carved: Vec<_> = frames.iter().map(Carved::new).collect();
+    let mut graph_cut = GraphCut::new(video_size(frames), &Options::default(), forward_energy);
+    for _ in 0..pixel_count {
+        let surface = {
+            let mut energies: Vec<_> = carved.iter().map(ImageEnergy).collect();
+            graph_cut.extract_surface(&mut energies)
+        };
+        for (frame, seam) in carved.iter_mut().zip(&surface) {
+            frame.remove_seam(seam);
+        }
+    }
+    carved
+}
+
+fn carve_frames<IMG: GenericImageView>(
+    frames: &[IMG],
+    pixel_count: u32,
+    temporal_weight: u32,
+) -> Vec<Carved<'_, IMG>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut carved = Vec::with_capacity(frames.len());
+    let mut previous: Option<Matrix<u32>> = None;
+    for frame in frames {
+        let mut frame = Carved::new(frame);
+        let size = max_pos(&frame);
+        // The penalty of a pixel only depends on its position in the original frame,
+        // so the finder can keep its cached values from one seam to the next.
+        let mut seam_finder = SeamFinder::new(size, &Options::default());
+        let mut removed = Matrix::from_fn(size, |_, _| false);
+        for _ in 0..pixel_count {
+            let seam = {
+                let img = &frame;
+                seam_finder.extract_seam(|p| {
+                    let original = img.transform_pos(p);
+                    let penalty = temporal_penalty(&previous, original, temporal_weight);
+                    energy_fn(img, p).saturating_add(penalty)
+                })
+            };
+            seam.iter().for_each(|&p| removed[frame.transform_pos(p)] = true);
+            frame.remove_seam(&seam);
+        }
+        previous = Some(distances(&removed, size));
+        carved.push(frame);
+    }
+    carved
+}
+
+/// Energy added to a position of the original frame
+/// depending on how far it is from the pixels removed from the previous frame
+#[inline]
+fn temporal_penalty(previous: &Option<Matrix<u32>>, pos: Pos, weight: u32) -> u32 {
+    previous.as_ref().map_or(0, |distances| distances[pos].saturating_mul(weight))
+}
+
+/// For every pixel, the distance to the closest removed pixel of its row,
+/// or `0` if no pixel of the row was removed
+fn distances(removed: &Matrix<bool>, size: Pos) -> Matrix<u32> {
+    let mut distances = Matrix::from_fn(size, |_, _| 0);
+    for y in 0..size.1 as usize {
+        let (removed, distances) = (removed.row(y), distances.row_mut(y));
+        if !removed.contains(&true) {
+            continue;
+        }
+        let mut distance = u32::MAX;
+        for (d, &r) in distances.iter_mut().zip(removed) {
+            distance = if r { 0 } else { distance.saturating_add(1) };
+            *d = distance;
+        }
+        for (d, &r) in distances.iter_mut().zip(removed).rev() {
+            distance = if r { 0 } else { distance.saturating_add(1) };
+            *d = (*d).min(distance);
+        }
+    }
+    distances
+}
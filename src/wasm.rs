@@ -0,0 +1,121 @@
+//! WebAssembly bindings, enabled with the `wasm` feature.
+//!
+//! Images are RGBA pixels stored row by row, as in the `data` of an
+//! [`ImageData`](https://developer.mozilla.org/docs/Web/API/ImageData).
+//! Build the module with
+//! `cargo rustc --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm --crate-type cdylib`
+//! followed by `wasm-bindgen`, then use it from JavaScript:
+//!
+//! ```js
+//! const carvable = new Carvable(imageData.data, imageData.width, imageData.height);
+//! slider.oninput = () => {
+//!     carvable.setWidth(slider.value);
+//!     context.putImageData(new ImageData(carvable.imageData(), carvable.width, carvable.height), 0, 0);
+//! };
+//! ```
+use std::mem::ManuallyDrop;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
+
+use crate::raw::{resize_raw, EnergySource, RawCarvable, RawImage, RawImageError};
+use crate::Options;
+
+/// Resizes an RGBA image to a lower width and height.
+/// Returns the pixels of the resized image, of size `min(targetWidth, width)`
+/// by `min(targetHeight, height)`.
+#[wasm_bindgen]
+pub fn resize(
+    data: Clamped<Vec<u8>>,
+    width: u32,
+    height: u32,
+    #[wasm_bindgen(js_name = targetWidth)] target_width: u32,
+    #[wasm_bindgen(js_name = targetHeight)] target_height: u32,
+) -> Result<Clamped<Vec<u8>>, JsError> {
+    let img = rgba(&data, width, height)?;
+    let (carved, _) = resize_raw(&img, target_width, target_height, &Options::default());
+    Ok(Clamped(carved))
+}
+
+/// An RGBA image from which vertical seams can be removed and restored one at a time
+#[wasm_bindgen]
+pub struct Carvable {
+    // Borrows the leaked pixels below, so it must be dropped before them
+    carvable: ManuallyDrop<RawCarvable<'static, u8>>,
+    pixels: &'static [u8],
+}
+
+#[wasm_bindgen]
+impl Carvable {
+    /// Takes the pixels of an image of the given size
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: Clamped<Vec<u8>>, width: u32, height: u32) -> Result<Carvable, JsError> {
+        // Checked before leaking the pixels
+        rgba(&data, width, height)?;
+        let pixels: &'static [u8] = Box::leak(data.0.into_boxed_slice());
+        let img = rgba(pixels, width, height)?;
+        let carvable = RawCarvable::new(img, EnergySource::Gradient, &Options::default());
+        Ok(Carvable {
+            carvable: ManuallyDrop::new(carvable),
+            pixels,
+        })
+    }
+
+    /// Removes the lowest-energy vertical seam.
+    /// Returns the column of its pixel on each row of the original image, from top to bottom,
+    /// or `undefined` if the image is empty.
+    #[wasm_bindgen(js_name = removeSeam)]
+    pub fn remove_seam(&mut self) -> Option<Vec<u32>> {
+        let seam = self.carvable.remove_seam()?;
+        Some(seam.into_iter().map(|(x, _)| x).collect())
+    }
+
+    /// Puts back the most recently removed seam.
+    /// Returns false if no seam has been removed.
+    #[wasm_bindgen(js_name = restoreSeam)]
+    pub fn restore_seam(&mut self) -> bool {
+        self.carvable.restore_seam()
+    }
+
+    /// Removes or restores seams until the image has the given width,
+    /// which cannot be larger than the original one.
+    /// Restored seams are removed again first, so going back to a width gives the same image.
+    #[wasm_bindgen(js_name = setWidth)]
+    pub fn set_width(&mut self, width: u32) {
+        self.carvable.set_width(width)
+    }
+
+    /// The current width of the image
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.carvable.dimensions().0
+    }
+
+    /// The current height of the image
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.carvable.dimensions().1
+    }
+
+    /// The pixels of the carved image, for the `ImageData` constructor
+    #[wasm_bindgen(js_name = imageData)]
+    pub fn image_data(&self) -> Clamped<Vec<u8>> {
+        Clamped(self.carvable.result())
+    }
+}
+
+impl Drop for Carvable {
+    fn drop(&mut self) {
+        // Safety: the carvable is dropped first, and nothing else borrows the pixels,
+        // which were leaked from a box in `new`
+        unsafe {
+            ManuallyDrop::drop(&mut self.carvable);
+            drop(Box::from_raw(self.pixels as *const [u8] as *mut [u8]));
+        }
+    }
+}
+
+/// Checks that `data` holds the RGBA pixels of an image of the given size
+fn rgba(data: &[u8], width: u32, height: u32) -> Result<RawImage<'_, u8>, RawImageError> {
+    RawImage::new(data, width, height, width as usize * 4, 4)
+}
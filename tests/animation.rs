@@ -0,0 +1,89 @@
+#![cfg(feature = "image")]
+
+use image::{Delay, Rgba, RgbaImage};
+
+use seamcarving::{read_apng, read_gif, resize_animation, write_apng, write_gif};
+use seamcarving::{AnimationFrame, Disposal};
+
+fn frame_from_row(row: &[u8], disposal: Disposal) -> AnimationFrame {
+    let width = row.len() as u32;
+    let buffer = RgbaImage::from_fn(width, 3, |x, _y| {
+        let v = row[x as usize];
+        Rgba([v, v, v, 255])
+    });
+    AnimationFrame {
+        buffer,
+        delay: Delay::from_numer_denom_ms(120, 1),
+        disposal,
+    }
+}
+
+fn animation() -> Vec<AnimationFrame> {
+    vec![
+        frame_from_row(&[9, 9, 9, 6, 3, 0], Disposal::Background),
+        frame_from_row(&[0, 3, 6, 9, 9, 9], Disposal::Previous),
+    ]
+}
+
+fn first_row(frame: &AnimationFrame) -> Vec<u8> {
+    let width = frame.buffer.width();
+    (0..width).map(|x| frame.buffer.get_pixel(x, 0)[0]).collect()
+}
+
+#[test]
+fn removes_the_same_seams_from_all_frames() {
+    let resized = resize_animation(&animation(), 5, 3);
+    assert_eq!(first_row(&resized[0]), vec![9, 9, 6, 3, 0]);
+    assert_eq!(first_row(&resized[1]), vec![3, 6, 9, 9, 9]);
+    assert_eq!(resized[1].delay, Delay::from_numer_denom_ms(120, 1));
+    assert_eq!(resized[1].disposal, Disposal::Previous);
+}
+
+#[test]
+fn gif_roundtrip() {
+    let mut encoded = vec![];
+    write_gif(&mut encoded, &animation()).unwrap();
+    let decoded = read_gif(&encoded[..]).unwrap();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[1].buffer.dimensions(), (6, 3));
+    assert_eq!(decoded[1].delay, Delay::from_numer_denom_ms(120, 1));
+    // Composited frames are cleared before the next one
+    let disposals: Vec<_> = decoded.iter().map(|f| f.disposal).collect();
+    assert_eq!(disposals, vec![Disposal::Background, Disposal::Background]);
+}
+
+#[test]
+fn gif_transparent_pixels_do_not_show_the_previous_frame() {
+    let opaque = frame_from_row(&[200; 4], Disposal::Keep);
+    let mut transparent = frame_from_row(&[0, 0, 0, 0], Disposal::Keep);
+    transparent.buffer.put_pixel(1, 1, Rgba([0, 0, 0, 0]));
+    let mut encoded = vec![];
+    write_gif(&mut encoded, &[opaque, transparent]).unwrap();
+    let decoded = read_gif(&encoded[..]).unwrap();
+    assert_eq!(decoded[1].buffer.get_pixel(1, 1)[3], 0);
+    assert_eq!(decoded[1].buffer.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn apng_roundtrip() {
+    let mut encoded = vec![];
+    write_apng(&mut encoded, &animation()).unwrap();
+    let decoded = read_apng(&encoded[..]).unwrap();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(first_row(&decoded[1]), vec![0, 3, 6, 9, 9, 9]);
+    assert_eq!(decoded[0].delay, Delay::from_numer_denom_ms(120, 1));
+    let disposals: Vec<_> = decoded.iter().map(|f| f.disposal).collect();
+    assert_eq!(disposals, vec![Disposal::Background, Disposal::Previous]);
+}
+
+#[test]
+fn still_png_is_a_single_frame() {
+    let mut encoded = vec![];
+    let still = RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 4]));
+    image::codecs::png::PngEncoder::new(&mut encoded)
+        .encode(still.as_raw(), 4, 4, image::ColorType::Rgba8)
+        .unwrap();
+    let decoded = read_apng(&encoded[..]).unwrap();
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].buffer, still);
+}
@@ -0,0 +1,13 @@
+#![cfg(feature = "capi")]
+//! The C program that calls the interface is in the capi-tests crate
+
+#[test]
+fn header_is_up_to_date() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/seamcarving.h"));
+    let checked_in = include_str!("../include/seamcarving.h");
+    assert!(
+        generated == checked_in,
+        "include/seamcarving.h differs from the header generated from src/capi.rs, in {}",
+        env!("OUT_DIR")
+    );
+}
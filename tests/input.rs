@@ -1,3 +1,5 @@
+#![cfg(feature = "image")]
+
 use image::{DynamicImage, GenericImageView};
 
 use seamcarving::resize;
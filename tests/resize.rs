@@ -1,6 +1,10 @@
+#![cfg(feature = "image")]
+
 use image::{GrayImage, ImageBuffer, Luma};
 
-use seamcarving::resize;
+use seamcarving::{carve_rows, replay, resize, resize_with_options, resize_with_plan};
+use seamcarving::{image_view_to_buffer, Carvable, CarvingPlan, Options, PlanError, Solver};
+use seamcarving::{resize_to_aspect, resize_with_costs, StopCriterion, TieBreak};
 
 fn pi_img_8_3() -> ImageBuffer<Luma<u8>, Vec<u8>> {
     GrayImage::from_raw(
@@ -81,5 +85,375 @@ fn single_pixel() {
     let img = GrayImage::from_raw(1, 1, vec![42]).unwrap();
     let resized = resize(&img, 0, 0);
     assert_eq!(resized.dimensions(), (0, 0));
-    assert_eq!(resized.into_raw(), vec![]);
+    assert_eq!(resized.into_raw(), Vec::<u8>::new());
+}
+
+#[test]
+fn enlarges_with_interpolated_pixels() {
+    let img = GrayImage::from_raw(3, 1, vec![0, 10, 20]).unwrap();
+    let enlarged = resize_with_options(&img, 4, 1, &Options::default());
+    assert_eq!(enlarged.into_raw(), vec![0, 5, 10, 20]);
+}
+
+#[test]
+fn enlarges_with_disjoint_seams() {
+    let img = GrayImage::from_raw(3, 1, vec![0, 10, 20]).unwrap();
+    let options = Options::default().max_stretch(1.);
+    let enlarged = resize_with_options(&img, 5, 1, &options);
+    // Each of the two lowest-energy pixels is duplicated once.
+    // The last one has no right neighbour to interpolate with.
+    assert_eq!(enlarged.into_raw(), vec![0, 5, 10, 20, 20]);
+}
+
+#[test]
+fn enlarges_in_several_steps() {
+    let options = Options::default().max_stretch(0.25);
+    let enlarged = resize_with_options(&pi_img_8_3(), 20, 5, &options);
+    assert_eq!(enlarged.dimensions(), (20, 5));
+}
+
+#[test]
+fn resize_does_not_enlarge() {
+    let resized = resize(&pi_img_8_3(), 10, 2);
+    assert_eq!(resized.dimensions(), (8, 2));
+}
+
+#[test]
+fn graph_cut_solver() {
+    for &forward_energy in &[false, true] {
+        let options = Options::default().solver(Solver::GraphCut { forward_energy });
+        let resized = resize_with_options(&pi_img_8_3(), 6, 2, &options);
+        assert_eq!(resized.dimensions(), (6, 2));
+    }
+    let options = Options::default().solver(Solver::GraphCut {
+        forward_energy: false,
+    });
+    let resized = resize_with_options(&pi_img_8_3(), 7, 3, &options);
+    assert_eq!(resized, resize(&pi_img_8_3(), 7, 3));
+}
+
+#[test]
+fn replays_a_plan() {
+    let (resized, plan) = resize_with_plan(&pi_img_8_3(), 6, 2, &Options::default());
+    assert_eq!(plan.final_dimensions(), (6, 2));
+    assert_eq!(plan.seams.len(), 3);
+    let decoded = CarvingPlan::from_bytes(&plan.to_bytes()).unwrap();
+    assert_eq!(replay(&pi_img_8_3(), &decoded), Ok(resized));
+    let other = GrayImage::new(8, 4);
+    assert_eq!(replay(&other, &plan), Err(PlanError::SizeMismatch));
+}
+
+#[test]
+fn restores_seams() {
+    let img = GrayImage::from_fn(30, 12, |x, y| Luma([((x * 7919 + y * 104_729) % 251) as u8]));
+    let mut carvable = Carvable::new(&img);
+    let mut results = vec![image_view_to_buffer(carvable.result())];
+    for _ in 0..10 {
+        carvable.remove_seam();
+        results.push(image_view_to_buffer(carvable.result()));
+    }
+    for expected in results.iter().rev().skip(1) {
+        assert!(carvable.restore_seam());
+        assert_eq!(&image_view_to_buffer(carvable.result()), expected);
+    }
+    assert!(!carvable.restore_seam());
+    carvable.set_width(22);
+    assert_eq!(image_view_to_buffer(carvable.result()), results[8]);
+    carvable.set_width(27);
+    assert_eq!(image_view_to_buffer(carvable.result()), results[3]);
+    carvable.set_width(100);
+    assert_eq!(image_view_to_buffer(carvable.result()), img);
+}
+
+#[test]
+fn moves_back_and_forth_between_widths() {
+    use seamcarving::raw::{EnergySource, RawCarvable, RawImage};
+    // Seams computed again on this image differ from the restored ones
+    let img = GrayImage::from_fn(40, 12, |x, y| Luma([((x * 7 + y * 3 + 2) % 4) as u8 * 2]));
+    let options = Options::default();
+    let mut carvable = Carvable::with_options(&img, &options);
+    let raw = RawImage::new(img.as_raw(), 40, 12, 40, 1).unwrap();
+    let mut raw_carvable = RawCarvable::new(raw, EnergySource::Gradient, &options);
+    let mut images = std::collections::HashMap::new();
+    for &width in &[20, 30, 20, 35, 25, 20, 38, 20] {
+        carvable.set_width(width);
+        raw_carvable.set_width(width);
+        let result = image_view_to_buffer(carvable.result());
+        assert_eq!(raw_carvable.result(), result.as_raw()[..]);
+        assert_eq!(images.entry(width).or_insert_with(|| result.clone()), &result);
+    }
+    // Removing another seam forgets the restored ones
+    carvable.set_width(30);
+    carvable.remove_seam();
+    carvable.set_width(20);
+    assert_eq!(carvable.plan().seams.len(), 20);
+    assert_eq!(replay(&img, &carvable.plan()), Ok(image_view_to_buffer(carvable.result())));
+}
+
+#[test]
+fn tie_breaks_on_flat_images() {
+    // Flat images only have ties, so every cached choice depends on the tie break
+    let policies = [TieBreak::Center, TieBreak::Random(1), TieBreak::Random(42)];
+    for &(width, height) in &[(9, 7), (16, 5), (31, 12), (40, 40)] {
+        let flat = GrayImage::from_pixel(width, height, Luma([7]));
+        let stripes = GrayImage::from_fn(width, height, |x, _| Luma([(x % 3) as u8 * 40]));
+        for img in &[flat, stripes] {
+            for (&tie_break, per_pass) in policies.iter().flat_map(|p| [(p, 1), (p, 3)]) {
+                let options = Options::default().tie_break(tie_break).seams_per_pass(per_pass);
+                let mut carvable = Carvable::with_options(img, &options);
+                carvable.remove_seams(width / 2);
+                (0..width / 4).for_each(|_| assert!(carvable.restore_seam()));
+                carvable.set_width(1);
+                let carved = image_view_to_buffer(carvable.result());
+                assert_eq!(carved.dimensions(), (1, height));
+                assert_eq!(replay(img, &carvable.plan()), Ok(carved));
+                let (resized, plan) = resize_with_plan(img, width / 2, height / 2, &options);
+                assert_eq!(replay(img, &plan), Ok(resized));
+            }
+        }
+    }
+    // A cached choice made stale by the restored seams was crossed by a later seam
+    let img = GrayImage::from_fn(26, 7, |x, y| Luma([((x * 7 + y * 3 + 1) % 3) as u8]));
+    let options = Options::default().tie_break(TieBreak::Random(1585));
+    let mut carvable = Carvable::with_options(&img, &options);
+    (0..13).for_each(|_| carvable.remove_seam());
+    (0..3).for_each(|_| assert!(carvable.restore_seam()));
+    (0..13).for_each(|_| carvable.remove_seam());
+    let carved = image_view_to_buffer(carvable.result());
+    assert_eq!(replay(&img, &carvable.plan()), Ok(carved));
+}
+
+#[test]
+fn removes_seams_in_batches() {
+    let img = GrayImage::from_fn(200, 10, |x, y| Luma([((x * 7919 + y * 104_729) % 251) as u8]));
+    let mut batched = Carvable::new(&img);
+    batched.remove_seams(50);
+    let mut one_by_one = Carvable::new(&img);
+    (0..50).for_each(|_| one_by_one.remove_seam());
+    assert_eq!(batched.plan(), one_by_one.plan());
+    let expected = image_view_to_buffer(one_by_one.result());
+    assert_eq!(image_view_to_buffer(batched.result()), expected);
+    batched.set_width(200);
+    assert_eq!(image_view_to_buffer(batched.result()), img);
+}
+
+#[test]
+fn several_seams_per_pass() {
+    let img = GrayImage::from_fn(60, 30, |x, y| Luma([((x * 7919 + y * 104_729) % 251) as u8]));
+    for &per_pass in &[2, 7, 100] {
+        let options = Options::default().seams_per_pass(per_pass);
+        let (resized, plan) = resize_with_plan(&img, 31, 20, &options);
+        assert_eq!(resized.dimensions(), (31, 20));
+        assert_eq!(replay(&img, &plan), Ok(resized));
+        let mut carvable = Carvable::with_options(&img, &options);
+        carvable.remove_seams(29);
+        assert_eq!(carvable.plan().seams, plan.seams[..29]);
+    }
+}
+
+#[test]
+fn pyramid_mode() {
+    let img = GrayImage::from_fn(64, 40, |x, y| Luma([((x * 7919 + y * 104_729) % 251) as u8]));
+    for &factor in &[2, 3, 8] {
+        let options = Options::default().pyramid(factor);
+        let (resized, plan) = resize_with_plan(&img, 21, 33, &options);
+        assert_eq!(resized.dimensions(), (21, 33));
+        assert_eq!(replay(&img, &plan), Ok(resized));
+    }
+    let options = Options::default().pyramid(4);
+    let (resized, _) = resize_with_plan(&img, 0, 0, &options);
+    assert_eq!(resized.dimensions(), (0, 0));
+}
+
+#[test]
+fn u8_images_match_the_generic_path() {
+    // Pixels with u16 channels take the generic path, with the same energies
+    fn check<P: image::Pixel<Subpixel = u8> + 'static, Q: image::Pixel<Subpixel = u16> + 'static>(
+        img: &ImageBuffer<P, Vec<u8>>,
+    ) {
+        let wide: ImageBuffer<Q, Vec<u16>> = ImageBuffer::from_raw(
+            img.width(),
+            img.height(),
+            img.iter().map(|&c| u16::from(c)).collect(),
+        )
+        .unwrap();
+        let resized = resize(img, 23, 17);
+        let expected: Vec<u8> = resize(&wide, 23, 17).iter().map(|&c| c as u8).collect();
+        assert_eq!(resized.into_raw(), expected);
+    }
+    let value = |x: u32, y: u32, c: u32| ((x * 7919 + y * 104_729 + c * 31) % 251) as u8;
+    let gray = GrayImage::from_fn(40, 30, |x, y| Luma([value(x, y, 0) / 16]));
+    check::<_, Luma<u16>>(&gray);
+    let rgb =
+        image::RgbImage::from_fn(40, 30, |x, y| image::Rgb([0, 1, 2].map(|c| value(x, y, c))));
+    check::<_, image::Rgb<u16>>(&rgb);
+    let rgba = image::RgbaImage::from_fn(40, 30, |x, y| {
+        image::Rgba([0, 1, 2, 3].map(|c| value(x, y, c)))
+    });
+    check::<_, image::Rgba<u16>>(&rgba);
+}
+
+#[test]
+fn u8_images_match_the_generic_path_on_random_flat_images() {
+    // Few gray levels give many seams of equal energy, which the cached
+    // choices of both paths must break in the same way
+    let mut state = 0x2545_f491_u32;
+    let mut random = move |n: u32| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state % n
+    };
+    for _ in 0..6_000 {
+        let (width, height, levels) = (6 + random(20), 4 + random(17), 2 + random(5));
+        let gray = GrayImage::from_fn(width, height, |_, _| Luma([random(levels) as u8 * 40]));
+        let wide: ImageBuffer<Luma<u16>, Vec<u16>> =
+            ImageBuffer::from_fn(width, height, |x, y| Luma([u16::from(gray[(x, y)][0])]));
+        let new_width = 1 + random(width);
+        let expected: Vec<u8> =
+            resize(&wide, new_width, height).iter().map(|&c| c as u8).collect();
+        assert_eq!(resize(&gray, new_width, height).into_raw(), expected, "{:?}", gray);
+    }
+}
+
+#[test]
+fn u8_images_saturate_like_the_generic_path() {
+    // Every pixel has the highest energy, so the costs of the seams saturate
+    let img = image::RgbaImage::from_fn(8, 9000, |x, y| {
+        image::Rgba([((x / 2 + y / 2) % 2) as u8 * 255; 4])
+    });
+    let wide: ImageBuffer<image::Rgba<u16>, Vec<u16>> =
+        ImageBuffer::from_raw(8, 9000, img.iter().map(|&c| u16::from(c)).collect()).unwrap();
+    let expected: Vec<u8> = resize(&wide, 6, 9000).iter().map(|&c| c as u8).collect();
+    assert_eq!(resize(&img, 6, 9000).into_raw(), expected);
+}
+
+#[test]
+fn carves_rows_in_strips() {
+    // The seams have to go through the flat region in the middle
+    let img = GrayImage::from_fn(60, 40, |x, y| match x {
+        20..=31 => Luma([100]),
+        _ => Luma([((x * 37 + y * 11) % 251) as u8]),
+    });
+    let rows = img.rows().map(|row| row.copied().collect::<Vec<_>>());
+    let carved: Vec<Luma<u8>> = carve_rows(rows, 54, 7, &Options::default()).flatten().collect();
+    let expected = resize(&img, 54, 40);
+    assert_eq!(carved.len(), expected.pixels().len());
+    assert!(carved.iter().eq(expected.pixels()));
+}
+
+#[test]
+#[should_panic(expected = "All the rows must have the same length as the first one, 10")]
+fn carved_rows_have_the_same_length_across_strips() {
+    let rows = (0..6).map(|y| vec![Luma([y as u8]); if y < 4 { 10 } else { 12 }]);
+    carve_rows(rows, 8, 4, &Options::default()).for_each(drop);
+}
+
+#[test]
+fn raw_slices_match_the_image_layer() {
+    use seamcarving::raw::{resize_raw, RawImage, RawImageError};
+    let value = |x: u32, y: u32, c: u32| ((x * 7919 + y * 104_729 + c * 31) % 251) as u8;
+    let gray = GrayImage::from_fn(40, 30, |x, y| Luma([value(x, y, 0) / 16]));
+    let raw = RawImage::new(gray.as_raw(), 40, 30, 40, 1).unwrap();
+    let (carved, plan) = resize_raw(&raw, 23, 17, &Options::default());
+    let (expected, expected_plan) = resize_with_plan(&gray, 23, 17, &Options::default());
+    assert_eq!(carved, expected.into_raw());
+    assert_eq!(plan, expected_plan);
+    // Rows padded with 5 elements that must be ignored
+    let rgb = image::RgbImage::from_fn(40, 30, |x, y| image::Rgb([0, 1, 2].map(|c| value(x, y, c))));
+    let padded: Vec<u8> = rgb.rows().flat_map(|row| row.flat_map(|p| p.0).chain([255; 5])).collect();
+    let raw = RawImage::new(&padded, 40, 30, 125, 3).unwrap();
+    let options = Options::default().seams_per_pass(3);
+    let (carved, plan) = resize_raw(&raw, 31, 25, &options);
+    let (expected, expected_plan) = resize_with_plan(&rgb, 31, 25, &options);
+    assert_eq!(carved, expected.into_raw());
+    assert_eq!(plan, expected_plan);
+    assert_eq!(RawImage::new(&padded, 40, 30, 119, 3).unwrap_err(), RawImageError::StrideTooSmall);
+    assert_eq!(RawImage::new(&padded[..3744], 40, 30, 125, 3).unwrap_err(), RawImageError::DataTooShort);
+}
+
+#[test]
+fn stops_before_costly_seams() {
+    // A flat band on the left of a textured image, of which 7 columns have no energy
+    let value = |x: u32, y: u32| if x < 8 { 0 } else { ((x * 7919 + y * 104_729) % 251) as u8 };
+    let img = GrayImage::from_fn(30, 10, |x, y| Luma([value(x, y)]));
+    let options = Options::default().stop_when(StopCriterion::MaxCost(0));
+    let (resized, plan, costs) = resize_with_costs(&img, 5, 10, &options);
+    assert_eq!(costs, vec![0; 7]);
+    assert_eq!(resized.dimensions(), (23, 10));
+    assert_eq!(replay(&img, &plan), Ok(resized));
+    // Without a criterion, the same seams are removed first
+    let (_, all, all_costs) = resize_with_costs(&img, 5, 10, &Options::default());
+    assert_eq!(all.seams[..7], plan.seams[..]);
+    assert!(all_costs[7] > 0);
+
+    let mut carvable = Carvable::with_options(&img, &options);
+    carvable.set_width(5);
+    assert_eq!(carvable.plan(), plan);
+    assert_eq!(carvable.seam_costs(), &costs[..]);
+    assert!(!carvable.try_remove_seam());
+    assert!(carvable.restore_seam());
+    assert_eq!(carvable.seam_costs(), &costs[1..]);
+
+    use seamcarving::raw::{resize_raw, RawImage};
+    let raw = RawImage::new(img.as_raw(), 30, 10, 30, 1).unwrap();
+    assert_eq!(resize_raw(&raw, 5, 10, &options).1, plan);
+}
+
+#[test]
+fn stops_when_costs_rise() {
+    let img = GrayImage::from_fn(40, 12, |x, y| Luma([((x * x * 7 + y * 31) % 256) as u8]));
+    let (_, all, all_costs) = resize_with_costs(&img, 10, 12, &Options::default());
+    let limit = 2 * all_costs[0].max(1);
+    let expected = all_costs.iter().take_while(|&&cost| cost <= limit).count();
+    assert!(expected > 0 && expected < 30);
+    let options = Options::default().stop_when(StopCriterion::MaxRatio(2.));
+    let (resized, plan, costs) = resize_with_costs(&img, 10, 12, &options);
+    assert_eq!(costs, all_costs[..expected]);
+    assert_eq!(plan.seams, all.seams[..expected]);
+    assert_eq!(resized.dimensions(), (40 - expected as u32, 12));
+}
+
+#[test]
+fn resizes_to_an_aspect_ratio() {
+    let noise = |x: u32, y: u32| ((x * 7919 + y * 104_729) % 251) as u8;
+    // Flat columns on the left: removing them is free
+    let flat_columns =
+        GrayImage::from_fn(40, 20, |x, y| Luma([if x < 25 { 0 } else { noise(x, y) }]));
+    let (resized, dimensions) = resize_to_aspect(&flat_columns, 1.);
+    assert_eq!(dimensions, (20, 20));
+    assert_eq!(resized.dimensions(), dimensions);
+    // Flat rows at the top: duplicating them is free, while every column has texture
+    let flat_rows = GrayImage::from_fn(40, 20, |x, y| Luma([if y < 10 { 0 } else { noise(x, y) }]));
+    assert_eq!(resize_to_aspect(&flat_rows, 40. / 28.).1, (40, 28));
+    // The same images, transposed, are too tall
+    let tall = GrayImage::from_fn(20, 40, |x, y| flat_columns[(y, x)]);
+    assert_eq!(resize_to_aspect(&tall, 1.).1, (20, 20));
+    let tall = GrayImage::from_fn(20, 40, |x, y| flat_rows[(y, x)]);
+    assert_eq!(resize_to_aspect(&tall, 28. / 40.).1, (28, 40));
+    // An image that already has the ratio is unchanged
+    assert_eq!(resize_to_aspect(&flat_rows, 2.).0, flat_rows);
+    // Ratios that cannot be reached give the closest dimensions
+    let small = GrayImage::from_fn(17, 8, |x, y| Luma([noise(x, y)]));
+    assert_eq!(resize_to_aspect(&small, 0.01).1, (1, 15));
+    assert_eq!(resize_to_aspect(&small, 100.).1, (33, 1));
+}
+
+#[test]
+fn copies_into_existing_buffer() {
+    let img = image::RgbImage::from_fn(16, 9, |x, y| image::Rgb([x as u8, y as u8, (x * y) as u8]));
+    let mut carvable = Carvable::new(&img);
+    (0..5).for_each(|_| carvable.remove_seam());
+    let mut out = image::RgbImage::new(100, 100);
+    carvable.result().copy_into(&mut out);
+    assert_eq!(out, image_view_to_buffer(carvable.result()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn plan_to_json() {
+    let (_, plan) = resize_with_plan(&pi_img_8_3(), 7, 2, &Options::default());
+    let json = serde_json::to_string(&plan).unwrap();
+    assert!(json.starts_with(r#"{"width":8,"height":3,"seams":[{"axis":"Vertical","offsets":["#));
+    assert_eq!(serde_json::from_str::<CarvingPlan>(&json).unwrap(), plan);
 }
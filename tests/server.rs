@@ -0,0 +1,93 @@
+#![cfg(feature = "server")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use image::GenericImageView;
+
+/// A server running on a free port, stopped when dropped
+struct TestServer {
+    child: Child,
+    address: String,
+}
+
+impl TestServer {
+    fn start() -> Self {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let mut child = Command::new(env!("CARGO_BIN_EXE_seamcarving-server"))
+            .args(["127.0.0.1:0".as_ref(), root.as_os_str(), "2".as_ref()])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("cannot start the server");
+        let mut line = String::new();
+        BufReader::new(child.stdout.as_mut().unwrap()).read_line(&mut line).unwrap();
+        let address = line.trim().trim_start_matches("Listening on http://").to_string();
+        TestServer { child, address }
+    }
+
+    /// Sends a request, and returns the status code, the headers and the body of the response
+    fn request(&self, method: &str, target: &str, body: &[u8]) -> (u16, String, Vec<u8>) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+            method,
+            target,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8(response[..end].to_vec()).unwrap();
+        let status = head[9..12].parse().unwrap();
+        (status, head, response[end + 4..].to_vec())
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn resizes_files_and_request_bodies() {
+    let server = TestServer::start();
+    let input = image::open(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/input.png")).unwrap();
+
+    let (status, head, body) = server.request("GET", "/resize?src=input.png&w=80&h=90", b"");
+    assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+    assert!(head.contains("image/png"));
+    let resized = image::load_from_memory(&body).unwrap();
+    assert_eq!(resized.dimensions(), (80, 90));
+    assert_eq!(resized.to_rgba8(), seamcarving::resize(&input, 80, 90));
+
+    // Narrower versions replay the first seams found for the wider one
+    let (status, _, body) = server.request("GET", "/resize?src=input.png&w=95&h=100", b"");
+    assert_eq!(status, 200);
+    let resized = image::load_from_memory(&body).unwrap();
+    assert_eq!(resized.to_rgba8(), seamcarving::resize(&input, 95, 100));
+
+    let png = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/input.png")).unwrap();
+    let (status, head, body) = server.request("POST", "/resize?w=50&h=40&format=jpeg", &png);
+    assert_eq!(status, 200);
+    assert!(head.contains("image/jpeg"));
+    assert_eq!(image::load_from_memory(&body).unwrap().dimensions(), (50, 40));
+}
+
+#[test]
+fn rejects_invalid_requests() {
+    let server = TestServer::start();
+    let status = |target: &str| server.request("GET", target, b"").0;
+    assert_eq!(status("/resize?src=input.png&w=10"), 400);
+    assert_eq!(status("/resize?src=input.png&w=10&h=10&format=tiff"), 400);
+    assert_eq!(status("/resize?src=missing.png&w=10&h=10"), 404);
+    assert_eq!(status("/resize?src=..%2FCargo.toml&w=10&h=10"), 403);
+    assert_eq!(status("/other"), 404);
+    assert_eq!(server.request("POST", "/resize?w=10&h=10", b"not an image").0, 415);
+}
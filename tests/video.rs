@@ -0,0 +1,92 @@
+#![cfg(feature = "image")]
+
+use image::{Delay, GrayImage, Rgba, RgbaImage};
+
+use seamcarving::{read_gif, resize_video, resize_video_surfaces, write_gif};
+use seamcarving::{AnimationFrame, Disposal};
+
+fn frame_from_row(row: &[u8]) -> GrayImage {
+    let width = row.len() as u32;
+    GrayImage::from_fn(width, 3, |x, _y| image::Luma([row[x as usize]]))
+}
+
+fn video() -> Vec<GrayImage> {
+    vec![
+        // The best seam is the leftmost column
+        frame_from_row(&[9, 9, 9, 6, 3, 0]),
+        // The best seam is on the right
+        frame_from_row(&[0, 3, 6, 9, 9, 9]),
+    ]
+}
+
+#[test]
+fn independent_frames_without_temporal_weight() {
+    let resized = resize_video(&video(), 5, 3, 0);
+    assert_eq!(resized.len(), 2);
+    assert_eq!(&resized[0].as_raw()[..5], &[9, 9, 6, 3, 0]);
+    assert_eq!(&resized[1].as_raw()[..5], &[0, 3, 6, 9, 9]);
+}
+
+#[test]
+fn seams_stay_close_to_the_previous_frame() {
+    let resized = resize_video(&video(), 5, 3, 100);
+    assert_eq!(&resized[0].as_raw()[..5], &[9, 9, 6, 3, 0]);
+    assert_eq!(&resized[1].as_raw()[..5], &[3, 6, 9, 9, 9]);
+}
+
+#[test]
+fn removes_the_pixels_removed_from_the_previous_frame() {
+    let frames = vec![
+        frame_from_row(&[0, 1, 2, 60, 61, 62, 120, 180, 240]),
+        frame_from_row(&[0, 60, 120, 121, 122, 180, 181, 182, 240]),
+    ];
+    let resized = resize_video(&frames, 7, 3, 0);
+    assert_eq!(&resized[0].as_raw()[..7], &[2, 60, 61, 62, 120, 180, 240]);
+    assert_eq!(&resized[1].as_raw()[..7], &[0, 60, 120, 122, 180, 182, 240]);
+    // Both frames lose their first two columns
+    let resized = resize_video(&frames, 7, 3, 100_000);
+    assert_eq!(&resized[1].as_raw()[..7], &[120, 121, 122, 180, 181, 182, 240]);
+}
+
+#[test]
+fn huge_temporal_weight_saturates() {
+    let resized = resize_video(&video(), 5, 3, u32::MAX);
+    assert_eq!(&resized[1].as_raw()[..5], &[3, 6, 9, 9, 9]);
+}
+
+#[test]
+fn resizes_both_dimensions() {
+    let resized = resize_video(&video(), 4, 2, 10);
+    assert!(resized.iter().all(|f| f.dimensions() == (4, 2)));
+}
+
+#[test]
+fn seam_surfaces() {
+    let resized = resize_video_surfaces(&video(), 5, 3, false);
+    // Both frames lose the same column, which has the lowest total energy
+    assert_eq!(&resized[0].as_raw()[..5], &[9, 9, 6, 3, 0]);
+    assert_eq!(&resized[1].as_raw()[..5], &[3, 6, 9, 9, 9]);
+    let resized = resize_video_surfaces(&video(), 4, 2, true);
+    assert!(resized.iter().all(|f| f.dimensions() == (4, 2)));
+}
+
+#[test]
+fn resizes_gif_frames() {
+    let frames: Vec<AnimationFrame> = [0u8, 255]
+        .iter()
+        .map(|&v| AnimationFrame {
+            buffer: RgbaImage::from_pixel(8, 4, Rgba([v, v, v, 255])),
+            delay: Delay::from_numer_denom_ms(100, 1),
+            disposal: Disposal::Keep,
+        })
+        .collect();
+    let mut encoded = vec![];
+    write_gif(&mut encoded, &frames).unwrap();
+    let decoded = read_gif(&encoded[..]).unwrap();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[1].buffer.dimensions(), (8, 4));
+    assert_eq!(decoded[1].delay, Delay::from_numer_denom_ms(100, 1));
+    let buffers: Vec<_> = decoded.into_iter().map(|f| f.buffer).collect();
+    let resized = resize_video(&buffers, 6, 4, 10);
+    assert_eq!(resized[1].get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "wasm")]
+// Native tests of the functions exported to JavaScript
+
+use wasm_bindgen::Clamped;
+
+use seamcarving::wasm::{resize, Carvable};
+
+fn rgba(width: u32, height: u32) -> Vec<u8> {
+    (0..width * height * 4).map(|i| (i * 7919 % 251) as u8).collect()
+}
+
+#[test]
+fn resizes_image_data() {
+    let data = rgba(20, 12);
+    let resized = resize(Clamped(data.clone()), 20, 12, 15, 10).unwrap();
+    assert_eq!(resized.len(), 15 * 10 * 4);
+    let unchanged = resize(Clamped(data.clone()), 20, 12, 30, 30).unwrap();
+    assert_eq!(unchanged.0, data);
+}
+
+#[test]
+fn carves_seam_by_seam() {
+    let data = rgba(20, 12);
+    let mut carvable = Carvable::new(Clamped(data.clone()), 20, 12).unwrap();
+    let seam = carvable.remove_seam().unwrap();
+    assert_eq!(seam.len(), 12);
+    carvable.set_width(15);
+    assert_eq!((carvable.width(), carvable.height()), (15, 12));
+    let expected = resize(Clamped(data.clone()), 20, 12, 15, 12).unwrap();
+    assert_eq!(carvable.image_data().0, expected.0);
+    // Moving the slider back restores the original pixels
+    carvable.set_width(20);
+    assert_eq!(carvable.image_data().0, data);
+    assert!(!carvable.restore_seam());
+}